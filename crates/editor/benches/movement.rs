@@ -0,0 +1,51 @@
+#[path = "../tests/reference/mod.rs"]
+mod reference;
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use editor::location::{CharIndex, Movement};
+use reference::Text;
+use ropey::Rope;
+
+fn text() -> String {
+    let mut text = String::new();
+    for i in 0..100_000 {
+        text.push_str(&format!("line {} with some text in it\n", i));
+    }
+    text
+}
+
+fn bench_movement(c: &mut Criterion, name: &str, movement: Movement, from_end: bool) {
+    let text = text();
+    let lines = Text::new(&text);
+    let rope = Rope::from(text);
+    let mut start = CharIndex(0);
+    if from_end {
+        start.move_to(&rope, Movement::FileEnd).unwrap();
+    }
+    let start_position = start.position(&rope);
+    let mut group = c.benchmark_group(name);
+    group.bench_function("stepping", |b| {
+        b.iter(|| {
+            let mut position = start_position;
+            _ = reference::move_to(&mut position, &lines, black_box(movement));
+            position
+        })
+    });
+    group.bench_function("arithmetic", |b| {
+        b.iter(|| {
+            let mut char_idx = start;
+            _ = char_idx.move_to(&rope, black_box(movement));
+            char_idx
+        })
+    });
+    group.finish();
+}
+
+fn movement(c: &mut Criterion) {
+    bench_movement(c, "right 10000", Movement::Right(10_000), false);
+    bench_movement(c, "left 10000", Movement::Left(10_000), true);
+    bench_movement(c, "down 50000", Movement::Down(50_000), false);
+}
+
+criterion_group!(benches, movement);
+criterion_main!(benches);
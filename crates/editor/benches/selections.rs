@@ -0,0 +1,37 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use editor::{
+    location::{CharIndex, Selection},
+    BufferData, WindowData,
+};
+use ropey::Rope;
+
+/// A screenful of a buffer with a selection on every word, as after
+/// selecting all matches of a common pattern.
+fn selections(c: &mut Criterion) {
+    let mut text = String::new();
+    for i in 0..10_000 {
+        text.push_str(&format!("line {} with some text in it\n", i));
+    }
+    let rope = Rope::from(text);
+    let mut editor = editor::EditorData::new();
+    let buffer = editor
+        .buffers
+        .insert(BufferData::scratch("bench", rope.clone()));
+    let mut window = WindowData::new(buffer);
+    for (i, c) in rope.chars().enumerate() {
+        if c == ' ' {
+            window.selections.insert(Selection {
+                start: CharIndex(i + 1),
+                end: CharIndex(i + 3),
+                desired_column: None,
+            });
+        }
+    }
+    let visible = rope.line_to_char(5_000)..rope.line_to_char(5_060);
+    c.bench_function("visible ranges of 50000 selections", |b| {
+        b.iter(|| window.selection_ranges(&rope, black_box(visible.clone())))
+    });
+}
+
+criterion_group!(benches, selections);
+criterion_main!(benches);
@@ -0,0 +1,231 @@
+use std::ops::Range;
+
+/// A region where two sequences differ: `old` in the first one was replaced
+/// by `new` in the second one. Either range may be empty.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hunk {
+    pub old: Range<usize>,
+    pub new: Range<usize>,
+}
+
+/// Finds where `line` of the old sequence ended up in the new one. Lines
+/// inside a changed hunk move to its start.
+pub fn map_line(hunks: &[Hunk], line: usize) -> usize {
+    let mut delta = 0isize;
+    for hunk in hunks {
+        if line < hunk.old.start {
+            break;
+        }
+        if line < hunk.old.end {
+            return hunk.new.start;
+        }
+        delta += hunk.new.len() as isize - hunk.old.len() as isize;
+    }
+    (line as isize + delta) as usize
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Edit {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// Computes the hunks that turn `old` into `new`, using Myers' algorithm.
+pub fn diff<T: PartialEq>(old: &[T], new: &[T]) -> Vec<Hunk> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let edits = shortest_edit(
+        &old[prefix..old.len() - suffix],
+        &new[prefix..new.len() - suffix],
+    );
+
+    let mut hunks = Vec::new();
+    let mut pending: Option<Hunk> = None;
+    let (mut x, mut y) = (prefix, prefix);
+    for edit in edits {
+        if edit == Edit::Equal {
+            hunks.extend(pending.take());
+            x += 1;
+            y += 1;
+            continue;
+        }
+        let hunk = pending.get_or_insert(Hunk {
+            old: x..x,
+            new: y..y,
+        });
+        if edit == Edit::Delete {
+            x += 1;
+            hunk.old.end = x;
+        } else {
+            y += 1;
+            hunk.new.end = y;
+        }
+    }
+    hunks.extend(pending);
+    hunks
+}
+
+fn shortest_edit<T: PartialEq>(a: &[T], b: &[T]) -> Vec<Edit> {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let max = n + m;
+    let at = |k: isize| (k + max + 1) as usize;
+    let mut v = vec![0isize; 2 * max as usize + 3];
+    let mut trace = Vec::new();
+    'search: for d in 0..=max {
+        trace.push(v.clone());
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && v[at(k - 1)] < v[at(k + 1)]) {
+                v[at(k + 1)]
+            } else {
+                v[at(k - 1)] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[at(k)] = x;
+            if x >= n && y >= m {
+                break 'search;
+            }
+        }
+    }
+
+    let mut edits = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().rev() {
+        let d = d as isize;
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && v[at(k - 1)] < v[at(k + 1)]) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let prev_x = v[at(prev_k)];
+        let prev_y = prev_x - prev_k;
+        while x > prev_x && y > prev_y {
+            edits.push(Edit::Equal);
+            x -= 1;
+            y -= 1;
+        }
+        if d > 0 {
+            edits.push(if x == prev_x {
+                Edit::Insert
+            } else {
+                Edit::Delete
+            });
+        }
+        x = prev_x;
+        y = prev_y;
+    }
+    edits.reverse();
+    edits
+}
+
+/// Result of merging two sets of changes made to the same base.
+pub struct Merge {
+    pub lines: Vec<String>,
+    pub conflicts: usize,
+}
+
+/// Three-way merges `ours` and `theirs`, which both started out as `base`.
+/// Changes that overlap and differ are kept side by side between conflict
+/// markers, together with the base version.
+pub fn merge<S: AsRef<str> + PartialEq>(base: &[S], ours: &[S], theirs: &[S]) -> Merge {
+    let ours_hunks = diff(base, ours);
+    let theirs_hunks = diff(base, theirs);
+    let mut lines = Vec::new();
+    let mut conflicts = 0;
+    let (mut i, mut j) = (0, 0);
+    let mut base_pos = 0;
+    // Offsets from base line numbers to ours/theirs line numbers, for the
+    // hunks already consumed.
+    let (mut ours_delta, mut theirs_delta) = (0isize, 0isize);
+    let shift = |line: usize, delta: isize| (line as isize + delta) as usize;
+    loop {
+        let start = match (ours_hunks.get(i), theirs_hunks.get(j)) {
+            (None, None) => break,
+            (Some(a), None) => a.old.start,
+            (None, Some(b)) => b.old.start,
+            (Some(a), Some(b)) => a.old.start.min(b.old.start),
+        };
+        lines.extend(
+            base[base_pos..start]
+                .iter()
+                .map(|line| line.as_ref().to_string()),
+        );
+
+        // Grow the chunk until no hunk from either side touches it.
+        let mut end = start;
+        let (first_i, first_j) = (i, j);
+        loop {
+            if let Some(a) = ours_hunks.get(i).filter(|a| a.old.start <= end) {
+                end = end.max(a.old.end);
+                i += 1;
+            } else if let Some(b) = theirs_hunks.get(j).filter(|b| b.old.start <= end) {
+                end = end.max(b.old.end);
+                j += 1;
+            } else {
+                break;
+            }
+        }
+
+        let ours_range = shift(start, ours_delta)..{
+            ours_delta += ours_hunks[first_i..i]
+                .iter()
+                .map(|h| h.new.len() as isize - h.old.len() as isize)
+                .sum::<isize>();
+            shift(end, ours_delta)
+        };
+        let theirs_range = shift(start, theirs_delta)..{
+            theirs_delta += theirs_hunks[first_j..j]
+                .iter()
+                .map(|h| h.new.len() as isize - h.old.len() as isize)
+                .sum::<isize>();
+            shift(end, theirs_delta)
+        };
+        let ours_chunk = &ours[ours_range];
+        let theirs_chunk = &theirs[theirs_range];
+        let chunk: &[S] = if first_j == j || ours_chunk == theirs_chunk {
+            ours_chunk
+        } else if first_i == i {
+            theirs_chunk
+        } else {
+            conflicts += 1;
+            lines.push(String::from("<<<<<<< buffer\n"));
+            lines.extend(ours_chunk.iter().map(|line| terminated(line.as_ref())));
+            lines.push(String::from("||||||| saved\n"));
+            lines.extend(
+                base[start..end]
+                    .iter()
+                    .map(|line| terminated(line.as_ref())),
+            );
+            lines.push(String::from("=======\n"));
+            lines.extend(theirs_chunk.iter().map(|line| terminated(line.as_ref())));
+            lines.push(String::from(">>>>>>> disk\n"));
+            &[]
+        };
+        lines.extend(chunk.iter().map(|line| line.as_ref().to_string()));
+        base_pos = end;
+    }
+    lines.extend(
+        base[base_pos..]
+            .iter()
+            .map(|line| line.as_ref().to_string()),
+    );
+    Merge { lines, conflicts }
+}
+
+fn terminated(line: &str) -> String {
+    if line.ends_with('\n') {
+        line.to_string()
+    } else {
+        format!("{}\n", line)
+    }
+}
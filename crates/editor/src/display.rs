@@ -0,0 +1,225 @@
+//! How the text of a line is laid out on a grid of terminal cells. Grapheme
+//! clusters take as many cells as a terminal gives them, and characters that
+//! can't be shown as they are get a visible escape instead: `^X` for ASCII
+//! control characters and `<U+xxxx>` for other invisible ones.
+//!
+//! Columns are zero-based char offsets into the line, like
+//! `ColumnIndex::zero_based`, and screen columns count cells from the start
+//! of the line. Tabs extend to the next multiple of `tab_width`.
+
+use crate::options::LineNumbers;
+use std::{borrow::Cow, ops::Range};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthChar;
+
+/// One grapheme cluster of a line, as drawn.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Glyph<'a> {
+    /// Char offset of the cluster in the line.
+    pub column: usize,
+    /// Number of chars in the cluster.
+    pub len: usize,
+    pub screen_column: usize,
+    /// Number of cells taken by `text`.
+    pub width: usize,
+    /// What to draw in those cells.
+    pub text: Cow<'a, str>,
+}
+
+/// Lays out the clusters of `line`. The final newline is drawn as a space so
+/// that a cursor on it is visible.
+pub fn glyphs(line: &str, tab_width: usize) -> impl Iterator<Item = Glyph<'_>> {
+    let mut column = 0;
+    let mut screen_column = 0;
+    line.graphemes(true).map(move |cluster| {
+        let (text, width) = match cluster {
+            "\t" => {
+                let width = tab_width - screen_column % tab_width;
+                (Cow::Owned(" ".repeat(width)), width)
+            }
+            _ => render(cluster),
+        };
+        let glyph = Glyph {
+            column,
+            len: cluster.chars().count(),
+            screen_column,
+            width,
+            text,
+        };
+        column += glyph.len;
+        screen_column += glyph.width;
+        glyph
+    })
+}
+
+fn render(cluster: &str) -> (Cow<'_, str>, usize) {
+    let mut chars = cluster.chars();
+    let first = chars.next().unwrap_or(' ');
+    match first {
+        '\n' => (Cow::Borrowed(" "), 1),
+        '\x7f' => (Cow::Borrowed("^?"), 2),
+        c if c.is_ascii_control() => {
+            let escaped = format!("^{}", char::from(c as u8 + b'@'));
+            (Cow::Owned(escaped), 2)
+        }
+        c if c.is_control() => escape(c),
+        _ => match cluster_width(cluster) {
+            // Lone combining marks and zero width spaces would vanish into
+            // the cell before them.
+            0 => escape(first),
+            width => (Cow::Borrowed(cluster), width),
+        },
+    }
+}
+
+fn escape(c: char) -> (Cow<'static, str>, usize) {
+    let escaped = format!("<U+{:04X}>", u32::from(c));
+    let width = escaped.len();
+    (Cow::Owned(escaped), width)
+}
+
+/// The number of cells a terminal uses for a cluster: the widest char in it,
+/// or two when an emoji presentation selector asks for it.
+pub fn cluster_width(cluster: &str) -> usize {
+    if cluster.contains('\u{fe0f}') {
+        return 2;
+    }
+    cluster
+        .chars()
+        .map(|c| c.width().unwrap_or(0))
+        .max()
+        .unwrap_or(0)
+        .min(2)
+}
+
+/// The screen column the cluster at `column` starts at. Columns past the
+/// end of the line continue one cell per char.
+pub fn screen_column(line: &str, column: usize, tab_width: usize) -> usize {
+    let mut end = 0;
+    let mut last_column = 0;
+    for glyph in glyphs(line, tab_width) {
+        if column < glyph.column + glyph.len {
+            return glyph.screen_column;
+        }
+        end = glyph.screen_column + glyph.width;
+        last_column = glyph.column + glyph.len;
+    }
+    end + (column - last_column)
+}
+
+/// The column of the cluster drawn at `screen_column`, which is the last one
+/// when the line is shorter than that.
+pub fn buffer_column(line: &str, screen_column: usize, tab_width: usize) -> usize {
+    let mut last = 0;
+    for glyph in glyphs(line, tab_width) {
+        if screen_column < glyph.screen_column + glyph.width {
+            return glyph.column;
+        }
+        last = glyph.column;
+    }
+    last
+}
+
+/// The number of cells the gutter before the text takes: the sign columns,
+/// then line numbers wide enough for `line_count` lines and a space.
+pub fn gutter_width(line_numbers: LineNumbers, line_count: usize, sign_columns: usize) -> usize {
+    let numbers = match line_numbers {
+        LineNumbers::Off => 0,
+        _ => line_count.max(1).to_string().len() + 1,
+    };
+    sign_columns + numbers
+}
+
+/// The number shown in the gutter for `line`, both zero-based, when the
+/// cursor is on `cursor_line`.
+pub fn line_number(line_numbers: LineNumbers, line: usize, cursor_line: usize) -> Option<usize> {
+    match line_numbers {
+        LineNumbers::Off => None,
+        LineNumbers::Absolute => Some(line + 1),
+        LineNumbers::Relative => Some(line.abs_diff(cursor_line)),
+        LineNumbers::Hybrid if line == cursor_line => Some(line + 1),
+        LineNumbers::Hybrid => Some(line.abs_diff(cursor_line)),
+    }
+}
+
+/// Drawn at the start of the rows a wrapped line continues on, after the
+/// line's indent.
+pub const CONTINUATION_MARKER: &str = "\u{21aa} ";
+
+/// One screen row of a line that is wrapped to fit the window.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Row {
+    /// Indices of the glyphs on the row.
+    pub glyphs: Range<usize>,
+    /// Cells before the first glyph, which hold the indent and the
+    /// continuation marker on all rows but the first.
+    pub prefix: usize,
+}
+
+/// Breaks a line into rows of at most `width` cells, after the last
+/// whitespace that fits if there is one. Continuation rows are indented like
+/// the line, unless that would leave less than half of the row for text.
+pub fn wrap(glyphs: &[Glyph], width: usize) -> Vec<Row> {
+    // The last glyph is usually the newline, which is drawn as a space.
+    let indent: usize = glyphs[..glyphs.len().saturating_sub(1)]
+        .iter()
+        .take_while(|glyph| is_whitespace(glyph))
+        .map(|glyph| glyph.width)
+        .sum();
+    let marker = CONTINUATION_MARKER.chars().count();
+    let continuation = if indent + marker <= width / 2 {
+        indent + marker
+    } else {
+        marker.min(width.saturating_sub(1))
+    };
+    let mut rows = Vec::new();
+    let mut prefix = 0;
+    let mut start = 0;
+    let mut col = 0;
+    let mut last_break = None;
+    let mut i = 0;
+    while i < glyphs.len() {
+        let glyph = &glyphs[i];
+        if col > 0 && col + glyph.width > width.saturating_sub(prefix).max(1) {
+            let end = match last_break {
+                Some(end) if end > start => end,
+                _ => i,
+            };
+            rows.push(Row {
+                glyphs: start..end,
+                prefix,
+            });
+            start = end;
+            i = end;
+            col = 0;
+            last_break = None;
+            prefix = continuation;
+            continue;
+        }
+        col += glyph.width;
+        if is_whitespace(glyph) {
+            last_break = Some(i + 1);
+        }
+        i += 1;
+    }
+    rows.push(Row {
+        glyphs: start..glyphs.len(),
+        prefix,
+    });
+    rows
+}
+
+/// The index of the row the cluster at `column` is on.
+pub fn row_of(rows: &[Row], glyphs: &[Glyph], column: usize) -> usize {
+    let glyph = glyphs
+        .iter()
+        .position(|glyph| column < glyph.column + glyph.len)
+        .unwrap_or(glyphs.len().saturating_sub(1));
+    rows.iter()
+        .position(|row| glyph < row.glyphs.end)
+        .unwrap_or(rows.len() - 1)
+}
+
+fn is_whitespace(glyph: &Glyph) -> bool {
+    glyph.text.chars().all(|c| c == ' ')
+}
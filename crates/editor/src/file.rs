@@ -0,0 +1,607 @@
+use anyhow::{format_err, Context as _, Result};
+use encoding_rs::{Encoder, EncoderResult, Encoding, UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1252};
+use ropey::Rope;
+use std::{
+    collections::hash_map::DefaultHasher,
+    ffi::OsString,
+    fs::{self, File, OpenOptions},
+    hash::Hasher,
+    io::{self, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// A file as read from disk, decoded and with its line endings normalized
+/// to `\n`. Binary files are kept as bytes instead, and `text` is empty.
+pub struct Contents {
+    pub text: Rope,
+    pub disk: DiskState,
+    pub line_ending: LineEnding,
+    pub encoding: TextEncoding,
+    pub binary: Option<Vec<u8>>,
+}
+
+/// How to interpret the bytes of a file.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ReadAs {
+    Detect,
+    Text(TextEncoding),
+    Binary,
+}
+
+pub fn read(path: &Path, read_as: ReadAs) -> Result<Contents> {
+    let (bytes, disk) = DiskState::read(path)?;
+    let (text, encoding) = match read_as {
+        ReadAs::Detect if is_binary(&bytes) => return Ok(Contents::binary(bytes, disk)),
+        ReadAs::Detect => TextEncoding::detect_and_decode(&bytes),
+        ReadAs::Text(encoding) => (encoding.decode(&bytes)?, encoding),
+        ReadAs::Binary => return Ok(Contents::binary(bytes, disk)),
+    };
+    let line_ending = LineEnding::detect(&text);
+    Ok(Contents {
+        text: Rope::from(normalize_line_endings(text, line_ending)),
+        disk,
+        line_ending,
+        encoding,
+        binary: None,
+    })
+}
+
+impl Contents {
+    fn binary(bytes: Vec<u8>, disk: DiskState) -> Contents {
+        Contents {
+            text: Rope::new(),
+            disk,
+            line_ending: LineEnding::Lf,
+            encoding: TextEncoding::UTF_8,
+            binary: Some(bytes),
+        }
+    }
+}
+
+/// Guesses from the start of the data whether it is text at all: NUL bytes
+/// or lots of control characters mean it isn't, unless a byte order mark or
+/// the byte pattern says it is UTF-16.
+fn is_binary(bytes: &[u8]) -> bool {
+    if Encoding::for_bom(bytes).is_some() {
+        return false;
+    }
+    let sample = &bytes[..bytes.len().min(8192)];
+    if guess_utf16(sample).is_some() {
+        return false;
+    }
+    let control = sample
+        .iter()
+        .filter(|&&byte| byte < 0x20 && !b"\t\n\r\x0c\x1b".contains(&byte))
+        .count();
+    sample.contains(&0) || control * 10 > sample.len()
+}
+
+/// The encoding of a file too big to read whole, guessed from its start, if
+/// it can be read page by page. That takes text in an encoding where every
+/// `\n` byte is a line break, so binary files and UTF-16 are read whole.
+pub fn pageable_encoding(path: &Path) -> io::Result<Option<TextEncoding>> {
+    let mut head = Vec::new();
+    File::open(path)?.take(64 * 1024).read_to_end(&mut head)?;
+    if is_binary(&head) || guess_utf16(&head).is_some() {
+        return Ok(None);
+    }
+    match Encoding::for_bom(&head) {
+        Some((encoding, _)) if encoding == UTF_8 => {
+            return Ok(Some(TextEncoding {
+                encoding,
+                bom: true,
+            }))
+        }
+        Some(_) => return Ok(None),
+        None => {}
+    }
+    Ok(Some(match std::str::from_utf8(&head) {
+        Ok(_) => TextEncoding::UTF_8,
+        // Valid up to a char cut off at the end of the sample.
+        Err(err) if err.error_len().is_none() => TextEncoding::UTF_8,
+        Err(_) => TextEncoding {
+            encoding: WINDOWS_1252,
+            bom: false,
+        },
+    }))
+}
+
+/// Writes `text` with every `\n` turned into `line_ending`, encoded with
+/// `encoding`.
+pub fn write_text(
+    text: &Rope,
+    line_ending: LineEnding,
+    encoding: TextEncoding,
+    writer: &mut dyn Write,
+) -> io::Result<()> {
+    let mut writer = EncodingWriter::new(encoding, writer)?;
+    for chunk in text.chunks() {
+        if line_ending == LineEnding::Lf {
+            writer.write_str(chunk)?;
+            continue;
+        }
+        let mut lines = chunk.split('\n');
+        if let Some(first) = lines.next() {
+            writer.write_str(first)?;
+        }
+        for line in lines {
+            writer.write_str(line_ending.as_str())?;
+            writer.write_str(line)?;
+        }
+    }
+    writer.finish()
+}
+
+/// The character encoding of a file, and whether it starts with a byte order
+/// mark.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct TextEncoding {
+    pub encoding: &'static Encoding,
+    pub bom: bool,
+}
+
+impl TextEncoding {
+    pub const UTF_8: TextEncoding = TextEncoding {
+        encoding: UTF_8,
+        bom: false,
+    };
+
+    /// Looks up an encoding by any of its WHATWG labels, like `latin1` or
+    /// `utf-16le`. UTF-16 gets a byte order mark, everything else doesn't.
+    pub fn from_label(label: &str) -> Result<TextEncoding> {
+        let encoding = Encoding::for_label(label.as_bytes())
+            .ok_or_else(|| format_err!("unknown encoding '{}'", label))?;
+        Ok(TextEncoding {
+            encoding,
+            bom: encoding == UTF_16LE || encoding == UTF_16BE,
+        })
+    }
+
+    pub fn name(self) -> &'static str {
+        self.encoding.name()
+    }
+
+    /// Sniffs a byte order mark first. Without one, the text is taken as
+    /// UTF-16 if it looks like it, then as UTF-8 if it is valid, and as
+    /// windows-1252 (a superset of Latin-1) otherwise, which never fails.
+    /// UTF-16 goes before UTF-8 because its zero bytes are valid UTF-8.
+    fn detect_and_decode(bytes: &[u8]) -> (String, TextEncoding) {
+        if let Some((encoding, _)) = Encoding::for_bom(bytes) {
+            let encoding = TextEncoding {
+                encoding,
+                bom: true,
+            };
+            if let Ok(text) = encoding.decode(bytes) {
+                return (text, encoding);
+            }
+        }
+        if let Some(encoding) = guess_utf16(bytes) {
+            let encoding = TextEncoding {
+                encoding,
+                bom: false,
+            };
+            if let Ok(text) = encoding.decode(bytes) {
+                return (text, encoding);
+            }
+        }
+        if let Ok(text) = std::str::from_utf8(bytes) {
+            return (text.to_owned(), TextEncoding::UTF_8);
+        }
+        let (text, _) = WINDOWS_1252.decode_without_bom_handling(bytes);
+        (
+            text.into_owned(),
+            TextEncoding {
+                encoding: WINDOWS_1252,
+                bom: false,
+            },
+        )
+    }
+
+    pub(crate) fn decode(self, bytes: &[u8]) -> Result<String> {
+        let bytes = match Encoding::for_bom(bytes) {
+            Some((encoding, len)) if self.bom && encoding == self.encoding => &bytes[len..],
+            _ => bytes,
+        };
+        self.encoding
+            .decode_without_bom_handling_and_without_replacement(bytes)
+            .map(|text| text.into_owned())
+            .ok_or_else(|| format_err!("file is not valid {}", self.name()))
+    }
+}
+
+/// Guesses UTF-16 without a byte order mark from how many of the high bytes
+/// are zero, which they are for ASCII text.
+fn guess_utf16(bytes: &[u8]) -> Option<&'static Encoding> {
+    let sample = &bytes[..bytes.len().min(4096) & !1];
+    if sample.is_empty() {
+        return None;
+    }
+    let units = sample.len() / 2;
+    let zeros_at = |offset: usize| {
+        sample
+            .iter()
+            .skip(offset)
+            .step_by(2)
+            .filter(|&&byte| byte == 0)
+            .count()
+    };
+    let (even, odd) = (zeros_at(0), zeros_at(1));
+    if odd * 10 >= units * 4 && even * 10 < units {
+        Some(UTF_16LE)
+    } else if even * 10 >= units * 4 && odd * 10 < units {
+        Some(UTF_16BE)
+    } else {
+        None
+    }
+}
+
+/// Encodes UTF-8 text on its way to `writer`. encoding_rs has no UTF-16
+/// encoder, so that is done by hand.
+struct EncodingWriter<'a> {
+    encoding: TextEncoding,
+    encoder: Option<Encoder>,
+    writer: &'a mut dyn Write,
+    buffer: Vec<u8>,
+}
+
+impl<'a> EncodingWriter<'a> {
+    fn new(encoding: TextEncoding, writer: &'a mut dyn Write) -> io::Result<EncodingWriter<'a>> {
+        let is_utf16 = encoding.encoding == UTF_16LE || encoding.encoding == UTF_16BE;
+        if encoding.bom {
+            if encoding.encoding == UTF_8 {
+                writer.write_all(b"\xEF\xBB\xBF")?;
+            } else if encoding.encoding == UTF_16LE {
+                writer.write_all(b"\xFF\xFE")?;
+            } else if encoding.encoding == UTF_16BE {
+                writer.write_all(b"\xFE\xFF")?;
+            }
+        }
+        let encoder = if encoding.encoding == UTF_8 || is_utf16 {
+            None
+        } else {
+            Some(encoding.encoding.new_encoder())
+        };
+        Ok(EncodingWriter {
+            encoding,
+            encoder,
+            writer,
+            buffer: vec![0; 4096],
+        })
+    }
+
+    fn write_str(&mut self, text: &str) -> io::Result<()> {
+        self.encode(text, false)
+    }
+
+    fn finish(mut self) -> io::Result<()> {
+        self.encode("", true)
+    }
+
+    fn encode(&mut self, mut text: &str, last: bool) -> io::Result<()> {
+        let encoder = match &mut self.encoder {
+            Some(encoder) => encoder,
+            None if self.encoding.encoding == UTF_16LE => {
+                for unit in text.encode_utf16() {
+                    self.writer.write_all(&unit.to_le_bytes())?;
+                }
+                return Ok(());
+            }
+            None if self.encoding.encoding == UTF_16BE => {
+                for unit in text.encode_utf16() {
+                    self.writer.write_all(&unit.to_be_bytes())?;
+                }
+                return Ok(());
+            }
+            None => return self.writer.write_all(text.as_bytes()),
+        };
+        loop {
+            let (result, read, written) =
+                encoder.encode_from_utf8_without_replacement(text, &mut self.buffer, last);
+            self.writer.write_all(&self.buffer[..written])?;
+            text = &text[read..];
+            match result {
+                EncoderResult::InputEmpty => return Ok(()),
+                EncoderResult::OutputFull => {}
+                EncoderResult::Unmappable(c) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("'{}' cannot be encoded as {}", c, self.encoding.name()),
+                    ))
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LineEnding {
+    Lf,
+    CrLf,
+    Cr,
+}
+
+impl LineEnding {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+            LineEnding::Cr => "\r",
+        }
+    }
+
+    /// The name used by `:set fileformat`.
+    pub fn name(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "unix",
+            LineEnding::CrLf => "dos",
+            LineEnding::Cr => "mac",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Result<LineEnding> {
+        match name {
+            "unix" => Ok(LineEnding::Lf),
+            "dos" => Ok(LineEnding::CrLf),
+            "mac" => Ok(LineEnding::Cr),
+            _ => Err(format_err!("unknown file format '{}'", name)),
+        }
+    }
+
+    /// Picks the most common line ending in `text`, preferring `\n` when
+    /// there are none or on a tie.
+    pub fn detect(text: &str) -> LineEnding {
+        let (mut lf, mut crlf, mut cr) = (0usize, 0usize, 0usize);
+        let mut bytes = text.bytes().peekable();
+        while let Some(byte) = bytes.next() {
+            match byte {
+                b'\n' => lf += 1,
+                b'\r' if bytes.peek() == Some(&b'\n') => {
+                    bytes.next();
+                    crlf += 1;
+                }
+                b'\r' => cr += 1,
+                _ => {}
+            }
+        }
+        if crlf > lf && crlf >= cr {
+            LineEnding::CrLf
+        } else if cr > lf && cr > crlf {
+            LineEnding::Cr
+        } else {
+            LineEnding::Lf
+        }
+    }
+}
+
+/// Turns the line endings of the file, as detected, into `\n`. Any other
+/// `\r` is kept as text, so that writing the text back with the same line
+/// ending gives the same bytes.
+pub fn normalize_line_endings(text: String, line_ending: LineEnding) -> String {
+    match line_ending {
+        LineEnding::Lf => text,
+        LineEnding::CrLf | LineEnding::Cr if !text.contains('\r') => text,
+        LineEnding::CrLf => text.replace("\r\n", "\n"),
+        LineEnding::Cr => text.replace('\r', "\n"),
+    }
+}
+
+/// What a file looked like on disk when it was last read or written, used to
+/// notice when something else changes it behind the editor's back.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DiskState {
+    pub modified: Option<SystemTime>,
+    pub len: u64,
+    pub hash: u64,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DiskChange {
+    Unchanged,
+    Modified,
+    Deleted,
+}
+
+impl DiskState {
+    pub fn new(metadata: &fs::Metadata, bytes: &[u8]) -> DiskState {
+        DiskState {
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+            hash: hash_bytes(bytes),
+        }
+    }
+
+    /// The state of a file that is too large to hash, so that only its size
+    /// and modification time are compared.
+    pub fn stat(path: &Path) -> io::Result<DiskState> {
+        let metadata = fs::metadata(path)?;
+        Ok(DiskState {
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+            hash: 0,
+        })
+    }
+
+    /// Reads the whole file, returning its contents and state together.
+    pub fn read(path: &Path) -> io::Result<(Vec<u8>, DiskState)> {
+        let bytes = fs::read(path)?;
+        let metadata = fs::metadata(path)?;
+        let state = DiskState::new(&metadata, &bytes);
+        Ok((bytes, state))
+    }
+
+    /// Compares the file at `path` against this state. The contents are only
+    /// hashed when the size or modification time differ, and a file that
+    /// was rewritten with identical contents counts as unchanged, in which
+    /// case `self` is updated to match it.
+    pub fn check(&mut self, path: &Path) -> io::Result<DiskChange> {
+        let metadata = match fs::metadata(path) {
+            Ok(metadata) => metadata,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(DiskChange::Deleted),
+            Err(err) => return Err(err),
+        };
+        if metadata.modified().ok() == self.modified && metadata.len() == self.len {
+            return Ok(DiskChange::Unchanged);
+        }
+        let (_, state) = DiskState::read(path)?;
+        if state.hash == self.hash {
+            *self = state;
+            Ok(DiskChange::Unchanged)
+        } else {
+            Ok(DiskChange::Modified)
+        }
+    }
+}
+
+fn hash_bytes(bytes: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    hasher.write(bytes);
+    hasher.finish()
+}
+
+/// Passes writes through while hashing them the same way as `hash_bytes`.
+struct HashingWriter<W> {
+    inner: W,
+    hasher: DefaultHasher,
+}
+
+impl<W: Write> Write for HashingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.hasher.write(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Replaces the file at `path` with whatever `write` produces, without ever
+/// leaving a partially written file behind.
+///
+/// The new contents go to a temporary file in the same directory, which is
+/// synced and then renamed over the target. Symlinks are followed, so the
+/// file they point to is replaced rather than the link itself. If `backup` is
+/// set, the previous contents are kept next to the target with a `~` suffix.
+/// Returns the state of the newly written file.
+pub fn write_atomic(
+    path: &Path,
+    backup: bool,
+    write: impl FnOnce(&mut dyn Write) -> io::Result<()>,
+) -> Result<DiskState> {
+    let target = resolve_target(path)?;
+    let original = fs::metadata(&target).ok();
+    let dir = match target.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    // Until it has the target's permissions, only the owner may read the new
+    // contents.
+    let (temp_path, temp_file) = create_temp_file(dir, &target, original.is_some())?;
+    let result = (|| -> Result<DiskState> {
+        if let Some(metadata) = &original {
+            // Changing the owner can clear the setuid and setgid bits, so
+            // the permissions go second.
+            copy_ownership(&temp_file, metadata);
+            temp_file.set_permissions(metadata.permissions())?;
+        }
+        let mut writer = HashingWriter {
+            inner: BufWriter::new(temp_file),
+            hasher: DefaultHasher::new(),
+        };
+        write(&mut writer)?;
+        let hash = writer.hasher.finish();
+        let file = writer.inner.into_inner().map_err(|err| err.into_error())?;
+        file.sync_all()?;
+        if backup && original.is_some() {
+            fs::copy(&target, with_suffix(&target, "~")).context("failed to write backup file")?;
+        }
+        fs::rename(&temp_path, &target)?;
+        sync_dir(dir);
+        let metadata = fs::metadata(&target)?;
+        Ok(DiskState {
+            modified: metadata.modified().ok(),
+            len: metadata.len(),
+            hash,
+        })
+    })();
+    if result.is_err() {
+        _ = fs::remove_file(&temp_path);
+    }
+    result.with_context(|| format!("failed to write '{}'", path.display()))
+}
+
+fn resolve_target(path: &Path) -> Result<PathBuf> {
+    match fs::canonicalize(path) {
+        Ok(target) => Ok(target),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(path.to_owned()),
+        Err(err) => Err(err.into()),
+    }
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Creates a new file next to `target`, readable only by the owner if
+/// `private` is set.
+fn create_temp_file(dir: &Path, target: &Path, private: bool) -> Result<(PathBuf, File)> {
+    let name = target.file_name().context("path has no file name")?;
+    for attempt in 0u32.. {
+        let mut temp_name = OsString::from(".");
+        temp_name.push(name);
+        temp_name.push(format!(".{}.{}.tmp", std::process::id(), attempt));
+        let temp_path = dir.join(temp_name);
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        if private {
+            owner_only(&mut options);
+        }
+        match options.open(&temp_path) {
+            Ok(file) => return Ok((temp_path, file)),
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(err) => {
+                return Err(err).with_context(|| {
+                    format!("failed to create a temporary file in '{}'", dir.display())
+                })
+            }
+        }
+    }
+    unreachable!()
+}
+
+#[cfg(unix)]
+fn owner_only(options: &mut OpenOptions) {
+    use std::os::unix::fs::OpenOptionsExt;
+    options.mode(0o600);
+}
+
+#[cfg(not(unix))]
+fn owner_only(_options: &mut OpenOptions) {}
+
+#[cfg(unix)]
+fn copy_ownership(file: &File, metadata: &fs::Metadata) {
+    use std::os::unix::fs::{fchown, MetadataExt};
+    // Only root may give a file away, but the group can often be kept.
+    if fchown(file, Some(metadata.uid()), Some(metadata.gid())).is_err() {
+        _ = fchown(file, None, Some(metadata.gid()));
+    }
+}
+
+#[cfg(not(unix))]
+fn copy_ownership(_file: &File, _metadata: &fs::Metadata) {}
+
+#[cfg(unix)]
+fn sync_dir(dir: &Path) {
+    // Makes the rename itself durable. Failure only weakens the guarantee,
+    // the file has already been replaced.
+    if let Ok(dir) = File::open(dir) {
+        _ = dir.sync_all();
+    }
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) {}
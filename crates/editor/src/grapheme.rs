@@ -0,0 +1,104 @@
+//! Extended grapheme cluster boundaries in ropes, so that the cursor never
+//! stops inside an emoji, a flag or a letter with combining accents.
+//!
+//! Indices are char indices into the given slice, which is usually a line.
+
+use ropey::RopeSlice;
+use unicode_segmentation::{GraphemeCursor, GraphemeIncomplete};
+
+/// The start of the cluster before the one `char_idx` is in, or of that one
+/// if `char_idx` is inside it. Returns 0 at the start of the slice.
+pub fn prev_boundary(slice: RopeSlice, char_idx: usize) -> usize {
+    backward(slice, char_idx, 1).0
+}
+
+/// The start of the cluster after the one `char_idx` is in. Returns the
+/// length of the slice at its end.
+pub fn next_boundary(slice: RopeSlice, char_idx: usize) -> usize {
+    forward(slice, char_idx, 1, slice.len_chars()).0
+}
+
+/// Steps back over up to `n` clusters from `char_idx`. Returns where that
+/// ended and how many clusters were crossed, which is less than `n` if the
+/// start of the slice was reached.
+pub fn backward(slice: RopeSlice, char_idx: usize, n: usize) -> (usize, usize) {
+    let mut byte_idx = slice.char_to_byte(char_idx);
+    let (mut chunk, mut chunk_byte_idx, _, _) = slice.chunk_at_byte(byte_idx);
+    let mut cursor = GraphemeCursor::new(byte_idx, slice.len_bytes(), true);
+    let mut crossed = 0;
+    while crossed < n {
+        match cursor.prev_boundary(chunk, chunk_byte_idx) {
+            Ok(None) => break,
+            Ok(Some(boundary)) => {
+                byte_idx = boundary;
+                crossed += 1;
+            }
+            Err(GraphemeIncomplete::PrevChunk) => {
+                let (prev, prev_byte_idx, _, _) = slice.chunk_at_byte(chunk_byte_idx - 1);
+                chunk = prev;
+                chunk_byte_idx = prev_byte_idx;
+            }
+            Err(GraphemeIncomplete::PreContext(end)) => {
+                let context = slice.chunk_at_byte(end - 1).0;
+                cursor.provide_context(context, end - context.len());
+            }
+            Err(err) => unreachable!("{:?}", err),
+        }
+    }
+    (slice.byte_to_char(byte_idx), crossed)
+}
+
+/// Steps forward over up to `n` clusters from `char_idx`, without going past
+/// `limit`, which must be a boundary. Returns where that ended and how many
+/// clusters were crossed.
+pub fn forward(slice: RopeSlice, char_idx: usize, n: usize, limit: usize) -> (usize, usize) {
+    let mut byte_idx = slice.char_to_byte(char_idx);
+    let limit = slice.char_to_byte(limit);
+    let (mut chunk, mut chunk_byte_idx, _, _) = slice.chunk_at_byte(byte_idx);
+    let mut cursor = GraphemeCursor::new(byte_idx, slice.len_bytes(), true);
+    let mut crossed = 0;
+    while crossed < n && byte_idx < limit {
+        match cursor.next_boundary(chunk, chunk_byte_idx) {
+            Ok(None) => break,
+            Ok(Some(boundary)) => {
+                byte_idx = boundary;
+                crossed += 1;
+            }
+            Err(GraphemeIncomplete::NextChunk) => {
+                chunk_byte_idx += chunk.len();
+                chunk = slice.chunk_at_byte(chunk_byte_idx).0;
+            }
+            Err(GraphemeIncomplete::PreContext(end)) => {
+                let context = slice.chunk_at_byte(end - 1).0;
+                cursor.provide_context(context, end - context.len());
+            }
+            Err(err) => unreachable!("{:?}", err),
+        }
+    }
+    (slice.byte_to_char(byte_idx), crossed)
+}
+
+pub fn is_boundary(slice: RopeSlice, char_idx: usize) -> bool {
+    let byte_idx = slice.char_to_byte(char_idx);
+    let (chunk, chunk_byte_idx, _, _) = slice.chunk_at_byte(byte_idx);
+    let mut cursor = GraphemeCursor::new(byte_idx, slice.len_bytes(), true);
+    loop {
+        match cursor.is_boundary(chunk, chunk_byte_idx) {
+            Ok(is_boundary) => return is_boundary,
+            Err(GraphemeIncomplete::PreContext(end)) => {
+                let context = slice.chunk_at_byte(end - 1).0;
+                cursor.provide_context(context, end - context.len());
+            }
+            Err(err) => unreachable!("{:?}", err),
+        }
+    }
+}
+
+/// `char_idx` if it starts a cluster, otherwise the start of its cluster.
+pub fn floor_boundary(slice: RopeSlice, char_idx: usize) -> usize {
+    if char_idx >= slice.len_chars() || is_boundary(slice, char_idx) {
+        char_idx
+    } else {
+        prev_boundary(slice, char_idx)
+    }
+}
@@ -0,0 +1,180 @@
+//! The hex view used for binary buffers, where the bytes are edited directly
+//! instead of through the rope.
+
+use crate::{location::Movement, BufferData, Edit, WindowAction, WindowData};
+use anyhow::{format_err, Context as _, Result};
+
+pub const BYTES_PER_ROW: usize = 16;
+
+/// Where the window's cursor is in a binary buffer. An offset equal to the
+/// length of the data is allowed and appends to it.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq)]
+pub struct HexCursor {
+    pub offset: usize,
+    /// Whether typing a digit sets the low half of the byte rather than the
+    /// high one.
+    pub low_nibble: bool,
+}
+
+impl HexCursor {
+    pub fn row(self) -> usize {
+        self.offset / BYTES_PER_ROW
+    }
+
+    /// Keeps the cursor on the same byte after an edit made through another
+    /// window.
+    pub fn follow_edit(&mut self, edit: &Edit) {
+        match *edit {
+            Edit::Insert { at, len } if self.offset > at => self.offset += len,
+            Edit::Remove(ref range) if self.offset > range.start => {
+                self.offset -= self.offset.min(range.end) - range.start;
+            }
+            _ => {}
+        }
+    }
+
+    /// Applies a text movement to the bytes: left and right step by bytes, up
+    /// and down by rows, and line starts and ends are row starts and ends.
+    pub fn move_to(&mut self, len: usize, movement: Movement) {
+        let row_start = self.offset - self.offset % BYTES_PER_ROW;
+        self.offset = match movement {
+            Movement::Left(n) => self.offset.saturating_sub(n),
+            Movement::Right(n) => self.offset.saturating_add(n).min(len),
+            Movement::Up(n) => self
+                .offset
+                .checked_sub(n.saturating_mul(BYTES_PER_ROW))
+                .unwrap_or(self.offset % BYTES_PER_ROW),
+            Movement::Down(n) => self
+                .offset
+                .saturating_add(n.saturating_mul(BYTES_PER_ROW))
+                .min(len),
+            Movement::LineStart => row_start,
+            Movement::LineEnd => (row_start + BYTES_PER_ROW - 1).min(len),
+            Movement::FileStart => 0,
+            Movement::FileEnd => len,
+        };
+        self.low_nibble = false;
+    }
+}
+
+pub fn perform_window_action(
+    window: &mut WindowData,
+    buffer: &mut BufferData,
+    action: WindowAction,
+    last_screen_height: Option<u16>,
+) -> Result<()> {
+    let bytes = buffer.binary.as_mut().context("buffer is not binary")?;
+    let cursor = &mut window.hex_cursor;
+    // Edits through other windows may have left the cursor past the end.
+    cursor.offset = cursor.offset.min(bytes.len());
+    match action {
+        WindowAction::InsertAtSelectionStart(c) | WindowAction::InsertAtSelectionEnd(c) => {
+            let digit = c
+                .to_digit(16)
+                .ok_or_else(|| format_err!("'{}' is not a hex digit", c))?
+                as u8;
+            if cursor.offset == bytes.len() {
+                bytes.push(0);
+                buffer.edits.push(Edit::Insert {
+                    at: cursor.offset,
+                    len: 1,
+                });
+            }
+            let byte = &mut bytes[cursor.offset];
+            if cursor.low_nibble {
+                *byte = (*byte & 0xf0) | digit;
+                cursor.offset += 1;
+                cursor.low_nibble = false;
+            } else {
+                *byte = (*byte & 0x0f) | (digit << 4);
+                cursor.low_nibble = true;
+            }
+            buffer.modified = true;
+        }
+        WindowAction::Delete => {
+            if cursor.offset < bytes.len() {
+                bytes.remove(cursor.offset);
+                buffer
+                    .edits
+                    .push(Edit::Remove(cursor.offset..cursor.offset + 1));
+                buffer.modified = true;
+            }
+            cursor.low_nibble = false;
+        }
+        WindowAction::Move(movement)
+        | WindowAction::ShiftStart(movement)
+        | WindowAction::ShiftEnd(movement) => {
+            cursor.move_to(bytes.len(), movement);
+        }
+        WindowAction::ScrollPageUp
+        | WindowAction::ScrollPageDown
+        | WindowAction::ScrollHalfPageUp
+        | WindowAction::ScrollHalfPageDown => {
+            if let Some(height) = last_screen_height {
+                let height = usize::from(height);
+                let movement = match action {
+                    WindowAction::ScrollPageUp => Movement::Up(height),
+                    WindowAction::ScrollPageDown => Movement::Down(height),
+                    WindowAction::ScrollHalfPageUp => Movement::Up(height / 2),
+                    WindowAction::ScrollHalfPageDown => Movement::Down(height / 2),
+                    _ => unreachable!(),
+                };
+                cursor.move_to(bytes.len(), movement);
+            }
+        }
+        WindowAction::ScrollViewUp(_)
+        | WindowAction::ScrollViewDown(_)
+        | WindowAction::CenterView
+        | WindowAction::ViewCursorAtTop
+        | WindowAction::ViewCursorAtBottom
+        | WindowAction::OrderSelections => {}
+        WindowAction::SwitchToMode(mode) => {
+            window.mode = mode;
+        }
+    }
+    Ok(())
+}
+
+/// Number of rows needed to show `len` bytes, including the row the append
+/// position is on.
+pub fn row_count(len: usize) -> usize {
+    len / BYTES_PER_ROW + 1
+}
+
+/// Formats a row as its offset, the hex bytes and an ASCII column:
+///
+/// `00000010  48 65 6c 6c 6f 0a                                 |Hello.|`
+pub fn format_row(bytes: &[u8], row: usize) -> String {
+    let start = (row * BYTES_PER_ROW).min(bytes.len());
+    let end = (start + BYTES_PER_ROW).min(bytes.len());
+    let chunk = &bytes[start..end];
+    let mut line = format!("{:08x} ", row * BYTES_PER_ROW);
+    for i in 0..BYTES_PER_ROW {
+        match chunk.get(i) {
+            Some(byte) => line.push_str(&format!(" {:02x}", byte)),
+            None => line.push_str("   "),
+        }
+    }
+    line.push_str("  |");
+    line.extend(chunk.iter().map(|&byte| ascii_char(byte)));
+    line.push('|');
+    line
+}
+
+/// Column of the first hex digit of the `i`th byte of a row in `format_row`.
+pub fn hex_column(i: usize) -> usize {
+    10 + i * 3
+}
+
+/// Column of the `i`th byte of a row in the ASCII part of `format_row`.
+pub fn ascii_column(i: usize) -> usize {
+    hex_column(BYTES_PER_ROW) + 2 + i
+}
+
+pub fn ascii_char(byte: u8) -> char {
+    if byte.is_ascii_graphic() || byte == b' ' {
+        char::from(byte)
+    } else {
+        '.'
+    }
+}
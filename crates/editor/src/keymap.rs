@@ -0,0 +1,296 @@
+//! What keys do. The frontends translate their key events into `Key` and
+//! leave the rest to this module, so that they all behave the same.
+
+use crate::{
+    check_disk_changes,
+    layout::{Direction, Orientation},
+    location::Movement,
+    perform_action, Action, BufferAction, CommandAction, EditorAction, EditorData, Importance,
+    Mode, WindowAction,
+};
+use log::trace;
+
+/// A key press, as far as the editor cares.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    /// A character. Return is `'\n'` and tab is `'\t'`.
+    Char(char),
+    Ctrl(char),
+    Left,
+    Right,
+    Up,
+    Down,
+    ShiftLeft,
+    ShiftRight,
+    ShiftUp,
+    ShiftDown,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Backspace,
+    Escape,
+    /// The frontend got the focus back, which is when files are checked for
+    /// changes on disk.
+    FocusIn,
+    /// A press of the main mouse button on the cell at `x` and `y`.
+    Click {
+        x: usize,
+        y: usize,
+    },
+}
+
+/// Turns a key press into editor actions and performs them. Errors are
+/// shown as messages.
+pub fn handle_key(editor: &mut EditorData, key: Key) {
+    trace!("key: {:?}", key);
+    if key == Key::FocusIn {
+        check_disk_changes(editor);
+        return;
+    }
+    if let Err(e) = actions(editor, key)
+        .into_iter()
+        .try_for_each(|action| perform_action(editor, action))
+    {
+        editor.pending_message = Some((Importance::Error, format!("{e:#}")));
+    }
+}
+
+/// The actions a key press stands for in the focused window's mode.
+pub fn actions(editor: &EditorData, key: Key) -> Vec<Action> {
+    if let Key::Click { x, y } = key {
+        return click_actions(editor, x, y);
+    }
+
+    let mut actions = Vec::new();
+    let hex = {
+        let window = &editor.windows[editor.focused_window()];
+        editor.buffers[window.buffer].binary.is_some()
+    };
+
+    if let Mode::Normal | Mode::Insert | Mode::Append = editor.windows[editor.focused_window()].mode
+    {
+        match key {
+            Key::Left => actions.push(Action::Window(WindowAction::Move(Movement::Left(1)))),
+            Key::Down => actions.push(Action::Window(WindowAction::Move(Movement::Down(1)))),
+            Key::Up => actions.push(Action::Window(WindowAction::Move(Movement::Up(1)))),
+            Key::Right => actions.push(Action::Window(WindowAction::Move(Movement::Right(1)))),
+            Key::Ctrl('u') => actions.push(Action::Window(WindowAction::ScrollHalfPageUp)),
+            Key::Ctrl('d') => actions.push(Action::Window(WindowAction::ScrollHalfPageDown)),
+            Key::Home => actions.push(Action::Window(WindowAction::Move(Movement::LineStart))),
+            Key::End => actions.push(Action::Window(WindowAction::Move(Movement::LineEnd))),
+            Key::Ctrl('b') | Key::PageUp => {
+                actions.push(Action::Window(WindowAction::ScrollPageUp));
+            }
+            Key::Ctrl('f') | Key::PageDown => {
+                actions.push(Action::Window(WindowAction::ScrollPageDown));
+            }
+            Key::Ctrl('y') => actions.push(Action::Window(WindowAction::ScrollViewUp(1))),
+            Key::Ctrl('e') => actions.push(Action::Window(WindowAction::ScrollViewDown(1))),
+            Key::Ctrl('p') => actions.push(Action::Editor(EditorAction::PreviousTab)),
+            Key::Ctrl('n') => actions.push(Action::Editor(EditorAction::NextTab)),
+            Key::ShiftLeft => {
+                actions.push(Action::Window(WindowAction::ShiftEnd(Movement::Left(1))))
+            }
+            Key::ShiftDown => {
+                actions.push(Action::Window(WindowAction::ShiftEnd(Movement::Down(1))))
+            }
+            Key::ShiftUp => actions.push(Action::Window(WindowAction::ShiftEnd(Movement::Up(1)))),
+            Key::ShiftRight => {
+                actions.push(Action::Window(WindowAction::ShiftEnd(Movement::Right(1))))
+            }
+            _ => {}
+        }
+    }
+
+    match editor.windows[editor.focused_window()].mode {
+        Mode::Normal => match key {
+            Key::Char('i') => {
+                actions.push(Action::Window(WindowAction::OrderSelections));
+                actions.push(Action::Window(WindowAction::SwitchToMode(Mode::Insert)));
+            }
+            Key::Char('c') => {
+                actions.push(Action::Window(WindowAction::Delete));
+                actions.push(Action::Window(WindowAction::SwitchToMode(Mode::Insert)));
+            }
+            Key::Char('a') => {
+                actions.push(Action::Window(WindowAction::OrderSelections));
+                actions.push(Action::Window(WindowAction::SwitchToMode(Mode::Append)));
+            }
+            Key::Char('A') => {
+                actions.push(Action::Window(WindowAction::Move(Movement::LineEnd)));
+                actions.push(Action::Window(WindowAction::SwitchToMode(Mode::Insert)));
+            }
+            Key::Char('o') => {
+                actions.push(Action::Window(WindowAction::Move(Movement::LineEnd)));
+                actions.push(Action::Window(WindowAction::InsertAtSelectionEnd('\n')));
+                actions.push(Action::Window(WindowAction::Move(Movement::Down(1))));
+                actions.push(Action::Window(WindowAction::Move(Movement::LineStart)));
+                actions.push(Action::Window(WindowAction::SwitchToMode(Mode::Insert)));
+            }
+            Key::Char('x') => {
+                //self.move_selections(self.focused, Movement::Line, false)?;
+            }
+            Key::Char('X') => {
+                //self.move_selections(self.focused, Movement::Line, true)?;
+            }
+            // Key::Char('C')
+            Key::Char('g') => {
+                actions.push(Action::Window(WindowAction::SwitchToMode(Mode::Goto {
+                    selecting: false,
+                })));
+            }
+            Key::Char('G') => {
+                actions.push(Action::Window(WindowAction::SwitchToMode(Mode::Goto {
+                    selecting: true,
+                })));
+            }
+            Key::Char('z') => actions.push(Action::Window(WindowAction::SwitchToMode(Mode::View))),
+            Key::Ctrl('w') => {
+                actions.push(Action::Window(WindowAction::SwitchToMode(Mode::Window)))
+            }
+            Key::Char(':') => {
+                actions.push(Action::Window(WindowAction::SwitchToMode(Mode::Command)))
+            }
+            Key::Char('h') => actions.push(Action::Window(WindowAction::Move(Movement::Left(1)))),
+            Key::Char('j') => actions.push(Action::Window(WindowAction::Move(Movement::Down(1)))),
+            Key::Char('k') => actions.push(Action::Window(WindowAction::Move(Movement::Up(1)))),
+            Key::Char('l') => actions.push(Action::Window(WindowAction::Move(Movement::Right(1)))),
+            Key::Char('H') => {
+                actions.push(Action::Window(WindowAction::ShiftEnd(Movement::Left(1))))
+            }
+            Key::Char('J') => {
+                actions.push(Action::Window(WindowAction::ShiftEnd(Movement::Down(1))))
+            }
+            Key::Char('K') => actions.push(Action::Window(WindowAction::ShiftEnd(Movement::Up(1)))),
+            Key::Char('L') => {
+                actions.push(Action::Window(WindowAction::ShiftEnd(Movement::Right(1))))
+            }
+            Key::Char('d') => actions.push(Action::Window(WindowAction::Delete)),
+            Key::Char('u') => actions.push(Action::Buffer(BufferAction::Undo)),
+            Key::Char('U') => actions.push(Action::Buffer(BufferAction::Redo)),
+            _ => {}
+        },
+        Mode::Goto { selecting } => {
+            let wrapper = |m| {
+                if selecting {
+                    Action::Window(WindowAction::ShiftEnd(m))
+                } else {
+                    Action::Window(WindowAction::Move(m))
+                }
+            };
+            let movement = match key {
+                Key::Char('h') => Some(Movement::LineStart),
+                Key::Char('j') => Some(Movement::FileEnd),
+                Key::Char('k') => Some(Movement::FileStart),
+                Key::Char('l') => Some(Movement::LineEnd),
+                _ => None,
+            };
+            if let Some(movement) = movement {
+                actions.push(wrapper(movement));
+            }
+            actions.push(Action::Window(WindowAction::SwitchToMode(Mode::Normal)))
+        }
+        Mode::Window => {
+            // Back to normal first, as the focus may move to another window.
+            actions.push(Action::Window(WindowAction::SwitchToMode(Mode::Normal)));
+            let action = match key {
+                Key::Char('h') | Key::Left => Some(EditorAction::FocusWindow(Direction::Left)),
+                Key::Char('j') | Key::Down => Some(EditorAction::FocusWindow(Direction::Down)),
+                Key::Char('k') | Key::Up => Some(EditorAction::FocusWindow(Direction::Up)),
+                Key::Char('l') | Key::Right => Some(EditorAction::FocusWindow(Direction::Right)),
+                Key::Char('w') | Key::Ctrl('w') => Some(EditorAction::FocusNextWindow),
+                Key::Char('s') => Some(EditorAction::SplitWindow(Orientation::Stacked)),
+                Key::Char('v') => Some(EditorAction::SplitWindow(Orientation::SideBySide)),
+                Key::Char('c') | Key::Char('q') => Some(EditorAction::CloseWindow),
+                Key::Char('+') => Some(EditorAction::ResizeWindow(Orientation::Stacked, 1)),
+                Key::Char('-') => Some(EditorAction::ResizeWindow(Orientation::Stacked, -1)),
+                Key::Char('>') => Some(EditorAction::ResizeWindow(Orientation::SideBySide, 1)),
+                Key::Char('<') => Some(EditorAction::ResizeWindow(Orientation::SideBySide, -1)),
+                _ => None,
+            };
+            if let Some(action) = action {
+                actions.push(Action::Editor(action));
+            }
+        }
+        Mode::View => {
+            let action = match key {
+                Key::Char('z') => Some(WindowAction::CenterView),
+                Key::Char('t') => Some(WindowAction::ViewCursorAtTop),
+                Key::Char('b') => Some(WindowAction::ViewCursorAtBottom),
+                _ => None,
+            };
+            if let Some(action) = action {
+                actions.push(Action::Window(action));
+            }
+            actions.push(Action::Window(WindowAction::SwitchToMode(Mode::Normal)))
+        }
+        mode @ Mode::Insert | mode @ Mode::Append => match key {
+            Key::Escape => actions.push(Action::Window(WindowAction::SwitchToMode(Mode::Normal))),
+            // The hex view moves its cursor by itself as digits are typed.
+            Key::Char(c) if hex => {
+                actions.push(Action::Window(WindowAction::InsertAtSelectionStart(c)));
+            }
+            Key::Char(c) => match mode {
+                Mode::Insert => {
+                    actions.push(Action::Window(WindowAction::InsertAtSelectionStart(c)));
+                    actions.push(Action::Window(WindowAction::ShiftStart(Movement::Right(1))));
+                    actions.push(Action::Window(WindowAction::ShiftEnd(Movement::Right(1))));
+                }
+                Mode::Append => {
+                    actions.push(Action::Window(WindowAction::ShiftEnd(Movement::Right(1))));
+                    actions.push(Action::Window(WindowAction::InsertAtSelectionEnd(c)));
+                }
+                _ => unreachable!(),
+            },
+            Key::Backspace => {
+                actions.push(Action::Window(WindowAction::Move(Movement::Left(1))));
+                actions.push(Action::Window(WindowAction::Delete));
+            }
+            _ => {}
+        },
+        Mode::Command => match key {
+            Key::Escape => {
+                actions.push(Action::Command(CommandAction::Clear));
+                actions.push(Action::Window(WindowAction::SwitchToMode(Mode::Normal)));
+            }
+            Key::Char('\t') => actions.push(Action::Command(CommandAction::Tab)),
+            Key::Ctrl('m') | Key::Char('\n') => {
+                actions.push(Action::Command(CommandAction::Return))
+            }
+            Key::Char(c) => actions.push(Action::Command(CommandAction::Character(c))),
+            Key::Backspace => actions.push(Action::Command(CommandAction::Backspace)),
+            _ => {}
+        },
+    }
+
+    actions
+}
+
+/// Clicks on the tab line focus the tab under them, and clicks on the `<`
+/// and `>` at its ends scroll through the hidden tabs.
+fn click_actions(editor: &EditorData, x: usize, y: usize) -> Vec<Action> {
+    if y != 0 {
+        return Vec::new();
+    }
+    let tabs = &editor.open_tabs;
+    if let Some(tab) = tabs.iter().position(|tab| {
+        tab.label
+            .as_ref()
+            .is_some_and(|columns| columns.contains(&x))
+    }) {
+        return vec![Action::Editor(EditorAction::FocusTab(tab))];
+    }
+    let shown: Vec<_> = tabs.iter().filter_map(|tab| tab.label.clone()).collect();
+    let hidden_before = tabs.first().is_some_and(|tab| tab.label.is_none());
+    let hidden_after = tabs.last().is_some_and(|tab| tab.label.is_none());
+    match (shown.first(), shown.last()) {
+        (Some(first), _) if hidden_before && x < first.start => {
+            vec![Action::Editor(EditorAction::PreviousTab)]
+        }
+        (_, Some(last)) if hidden_after && x >= last.end => {
+            vec![Action::Editor(EditorAction::NextTab)]
+        }
+        _ => Vec::new(),
+    }
+}
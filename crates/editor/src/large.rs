@@ -0,0 +1,278 @@
+//! Paged access to files too big to load into a rope at once. Only the page
+//! being viewed is decoded, along with pages that were edited and then moved
+//! away from, and lines are counted by a background thread.
+
+use crate::file::{self, normalize_line_endings, LineEnding, TextEncoding};
+use anyhow::Result;
+use encoding_rs::UTF_8;
+use log::error;
+use ropey::Rope;
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{self, Read, Seek, SeekFrom, Write},
+    ops::Range,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+/// Pages cover this many bytes, rounded to the next line start, or to the
+/// next char in a line longer than a page.
+pub const PAGE_SIZE: u64 = 1 << 20;
+
+pub struct LargeFile {
+    file: File,
+    pub len: u64,
+    /// Index of the page shown in the buffer's `content`.
+    pub page: u64,
+    /// The dominant line ending, detected from the first page.
+    pub line_ending: LineEnding,
+    /// Detected from the start of the file by `file::pageable_encoding`.
+    /// A byte order mark is only at the start of the first page.
+    pub encoding: TextEncoding,
+    /// Pages that were edited and are not current, by index.
+    edited: BTreeMap<u64, Rope>,
+    pub lines: Arc<LineCount>,
+}
+
+/// Results of counting the lines of a large file in the background.
+#[derive(Default)]
+pub struct LineCount {
+    /// For every page, the number of lines before its first one.
+    lines_before_page: Mutex<Vec<usize>>,
+    total: Mutex<Option<usize>>,
+    pub scanned_bytes: AtomicU64,
+    cancelled: AtomicBool,
+}
+
+impl LineCount {
+    pub fn lines_before_page(&self, page: u64) -> Option<usize> {
+        let lines = self.lines_before_page.lock().unwrap();
+        lines.get(page as usize).copied()
+    }
+
+    /// The number of lines in the file, once counting has finished.
+    pub fn total(&self) -> Option<usize> {
+        *self.total.lock().unwrap()
+    }
+}
+
+impl LargeFile {
+    /// Opens the file and starts counting its lines. The first page is made
+    /// current.
+    pub fn open(path: &Path, encoding: TextEncoding) -> Result<LargeFile> {
+        let file = File::open(path)?;
+        let len = file.metadata()?.len();
+        let lines = Arc::new(LineCount::default());
+        let counter = File::open(path)?;
+        let thread_lines = Arc::clone(&lines);
+        thread::spawn(move || {
+            if let Err(err) = count_lines(counter, &thread_lines) {
+                error!("failed to count lines: {}", err);
+            }
+        });
+        let mut large = LargeFile {
+            file,
+            len,
+            page: 0,
+            line_ending: LineEnding::Lf,
+            encoding,
+            edited: BTreeMap::new(),
+            lines,
+        };
+        let first_page = large.read_text(0)?;
+        large.line_ending = LineEnding::detect(&first_page);
+        Ok(large)
+    }
+
+    /// The number of lines before the current page, so that its lines can
+    /// be numbered from the start of the file. Zero until they are counted.
+    pub fn lines_before_current_page(&self) -> usize {
+        self.lines.lines_before_page(self.page).unwrap_or(0)
+    }
+
+    pub fn page_count(&self) -> u64 {
+        self.len.div_ceil(PAGE_SIZE).max(1)
+    }
+
+    pub fn is_last_page(&self) -> bool {
+        self.page + 1 >= self.page_count()
+    }
+
+    /// The text of a page, with its edits if it has any.
+    pub fn read_page(&self, page: u64) -> Result<Rope> {
+        if let Some(text) = self.edited.get(&page) {
+            return Ok(text.clone());
+        }
+        let text = self.read_text(page)?;
+        Ok(Rope::from(normalize_line_endings(text, self.line_ending)))
+    }
+
+    /// Makes `page` current and returns its text. `current` is the text of
+    /// the page being left, which is kept if it differs from the file.
+    pub fn switch_page(&mut self, current: Rope, page: u64) -> Result<Rope> {
+        if self.is_edited(&current)? {
+            self.edited.insert(self.page, current);
+        } else {
+            self.edited.remove(&self.page);
+        }
+        let text = self.read_page(page)?;
+        self.page = page;
+        Ok(text)
+    }
+
+    /// Whether `current`, the text of the current page, differs from the
+    /// file.
+    fn is_edited(&self, current: &Rope) -> io::Result<bool> {
+        let original = self.read_text(self.page)?;
+        Ok(*current != normalize_line_endings(original, self.line_ending).as_str())
+    }
+
+    /// Writes the whole file, copying the pages that were not edited
+    /// byte for byte.
+    pub fn write_to(&self, current: &Rope, writer: &mut dyn Write) -> io::Result<()> {
+        for page in 0..self.page_count() {
+            let edited = if page != self.page {
+                self.edited.get(&page)
+            } else if self.is_edited(current)? {
+                Some(current)
+            } else {
+                None
+            };
+            match edited {
+                Some(text) => {
+                    let encoding = TextEncoding {
+                        bom: self.encoding.bom && page == 0,
+                        ..self.encoding
+                    };
+                    file::write_text(text, self.line_ending, encoding, writer)?
+                }
+                None => {
+                    let range = self.page_range(page)?;
+                    let mut file = &self.file;
+                    file.seek(SeekFrom::Start(range.start))?;
+                    io::copy(&mut file.take(range.end - range.start), writer)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Pages start at the first line that starts at or after a multiple of
+    /// `PAGE_SIZE`, so every page can be found without reading the ones
+    /// before it.
+    fn page_range(&self, page: u64) -> io::Result<Range<u64>> {
+        Ok(self.page_start(page)?..self.page_start(page + 1)?)
+    }
+
+    /// Where page `page` starts: after the first newline at or after
+    /// `page * PAGE_SIZE - 1`. A page is never longer than twice
+    /// `PAGE_SIZE`, so when no line starts before the next multiple, the
+    /// page starts at the first char at or after `page * PAGE_SIZE`
+    /// instead, in the middle of a line.
+    fn page_start(&self, page: u64) -> io::Result<u64> {
+        let offset = page * PAGE_SIZE;
+        if offset == 0 || offset >= self.len {
+            return Ok(offset.min(self.len));
+        }
+        let mut file = &self.file;
+        file.seek(SeekFrom::Start(offset - 1))?;
+        let mut window = Vec::new();
+        file.take(PAGE_SIZE).read_to_end(&mut window)?;
+        if let Some(i) = window.iter().position(|&byte| byte == b'\n') {
+            return Ok(offset + i as u64);
+        }
+        // Bytes after the first one that continue a UTF-8 char.
+        let continuation = if self.encoding.encoding == UTF_8 {
+            window[1..]
+                .iter()
+                .take_while(|&&byte| byte & 0xc0 == 0x80)
+                .count()
+        } else {
+            0
+        };
+        Ok((offset + continuation as u64).min(self.len))
+    }
+
+    /// The decoded text of a page as it is in the file. A page that isn't
+    /// valid in the encoding is an error rather than being decoded lossily,
+    /// which would change it when it is written back.
+    fn read_text(&self, page: u64) -> io::Result<String> {
+        let range = self.page_range(page)?;
+        let mut file = &self.file;
+        file.seek(SeekFrom::Start(range.start))?;
+        let mut bytes = Vec::with_capacity((range.end - range.start) as usize);
+        file.take(range.end - range.start).read_to_end(&mut bytes)?;
+        self.encoding.decode(&bytes).map_err(|err| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("page {}: {}", page + 1, err),
+            )
+        })
+    }
+}
+
+impl Drop for LargeFile {
+    fn drop(&mut self) {
+        self.lines.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Counts the lines of `file`, recording where each page starts on the way.
+/// Page `k` starts after the first newline at or after `k * PAGE_SIZE - 1`,
+/// or in the middle of a line if there is none before the boundary of page
+/// `k + 1`, as in `LargeFile::page_start`.
+fn count_lines(mut file: File, count: &LineCount) -> io::Result<()> {
+    let mut buffer = vec![0; 64 * 1024];
+    let mut newlines = 0;
+    let mut offset = 0u64;
+    let mut next_boundary = PAGE_SIZE - 1;
+    // Whether the boundary of the last page was passed but its first line
+    // wasn't found yet.
+    let mut waiting = false;
+    let mut last_byte = b'\n';
+    count.lines_before_page.lock().unwrap().push(0);
+    loop {
+        if count.cancelled.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        for (i, &byte) in buffer[..read].iter().enumerate() {
+            if offset + i as u64 == next_boundary {
+                if waiting {
+                    // A line longer than a page; the page starts in it.
+                    count.lines_before_page.lock().unwrap().push(newlines);
+                }
+                waiting = true;
+                next_boundary += PAGE_SIZE;
+            }
+            if byte == b'\n' {
+                newlines += 1;
+                if waiting {
+                    count.lines_before_page.lock().unwrap().push(newlines);
+                    waiting = false;
+                }
+            }
+        }
+        last_byte = buffer[read - 1];
+        offset += read as u64;
+        count.scanned_bytes.store(offset, Ordering::Relaxed);
+    }
+    if waiting {
+        count.lines_before_page.lock().unwrap().push(newlines);
+    }
+    let total = if last_byte == b'\n' {
+        newlines
+    } else {
+        newlines + 1
+    };
+    *count.total.lock().unwrap() = Some(total);
+    Ok(())
+}
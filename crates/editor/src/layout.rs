@@ -0,0 +1,318 @@
+//! How the windows of a tab share its area: a tree of splits with windows at
+//! the leaves. Neighbouring windows are kept a cell apart for the border
+//! between them.
+
+use crate::{render::Rect, WindowId};
+use std::ops::Range;
+
+/// How the children of a split are placed.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Orientation {
+    /// Above one another, as `:split` places them.
+    Stacked,
+    /// Next to one another, as `:vsplit` places them.
+    SideBySide,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Direction {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Layout {
+    Window(WindowId),
+    Split {
+        orientation: Orientation,
+        /// Each child with the rows or columns it was last given. The sizes
+        /// are scaled to fit when the area changes.
+        children: Vec<(Layout, usize)>,
+    },
+}
+
+/// The line between two neighbouring windows.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Border {
+    pub area: Rect,
+    /// The orientation of the split, so side by side windows have a
+    /// vertical border.
+    pub orientation: Orientation,
+}
+
+pub struct Tab {
+    pub layout: Layout,
+    pub focused: WindowId,
+    /// The area the layout was last drawn in.
+    pub area: Option<Rect>,
+    /// The columns of the tab line the label was last drawn in, if it fit.
+    pub label: Option<Range<usize>>,
+}
+
+impl Tab {
+    pub fn new(window: WindowId) -> Tab {
+        Tab {
+            layout: Layout::Window(window),
+            focused: window,
+            area: None,
+            label: None,
+        }
+    }
+
+    /// Where each window was last drawn, or would be on an 80 by 24 screen
+    /// if the tab wasn't drawn yet.
+    pub fn areas(&self) -> Vec<(WindowId, Rect)> {
+        let area = self.area.unwrap_or(Rect {
+            x: 0,
+            y: 0,
+            width: 80,
+            height: 22,
+        });
+        let mut windows = Vec::new();
+        self.layout
+            .clone()
+            .arrange(area, &mut windows, &mut Vec::new());
+        windows
+    }
+
+    /// The window next to the focused one in `direction`. Of several, the
+    /// one closest to the focused window's top left corner is taken.
+    pub fn neighbour(&self, direction: Direction) -> Option<WindowId> {
+        let areas = self.areas();
+        let from = areas.iter().find(|(id, _)| *id == self.focused)?.1;
+        // Looking up and down is looking left and right with the axes
+        // swapped.
+        let (from, flip) = match direction {
+            Direction::Left | Direction::Right => (from, false),
+            Direction::Up | Direction::Down => (transpose(from), true),
+        };
+        let forward = matches!(direction, Direction::Right | Direction::Down);
+        areas
+            .iter()
+            .filter(|(id, _)| *id != self.focused)
+            .map(|&(id, area)| (id, if flip { transpose(area) } else { area }))
+            .filter(|(_, area)| area.y < from.y + from.height && from.y < area.y + area.height)
+            .filter(|(_, area)| {
+                if forward {
+                    area.x >= from.x + from.width
+                } else {
+                    area.x + area.width <= from.x
+                }
+            })
+            .min_by_key(|(_, area)| {
+                let distance = if forward {
+                    area.x - (from.x + from.width)
+                } else {
+                    from.x - (area.x + area.width)
+                };
+                (distance, area.y.abs_diff(from.y))
+            })
+            .map(|(id, _)| id)
+    }
+}
+
+fn transpose(area: Rect) -> Rect {
+    Rect {
+        x: area.y,
+        y: area.x,
+        width: area.height,
+        height: area.width,
+    }
+}
+
+impl Layout {
+    /// The windows from the top left to the bottom right.
+    pub fn windows(&self) -> Vec<WindowId> {
+        match self {
+            Layout::Window(id) => vec![*id],
+            Layout::Split { children, .. } => children
+                .iter()
+                .flat_map(|(child, _)| child.windows())
+                .collect(),
+        }
+    }
+
+    pub fn contains(&self, window: WindowId) -> bool {
+        match self {
+            Layout::Window(id) => *id == window,
+            Layout::Split { children, .. } => {
+                children.iter().any(|(child, _)| child.contains(window))
+            }
+        }
+    }
+
+    /// Splits the space of `window` with `new`, which goes below or to the
+    /// right of it. Returns whether `window` was found.
+    pub fn split(&mut self, window: WindowId, new: WindowId, orientation: Orientation) -> bool {
+        match self {
+            Layout::Window(id) if *id == window => {
+                *self = Layout::Split {
+                    orientation,
+                    children: vec![(Layout::Window(window), 1), (Layout::Window(new), 1)],
+                };
+                true
+            }
+            Layout::Window(_) => false,
+            Layout::Split {
+                orientation: split_orientation,
+                children,
+            } => {
+                let position = children
+                    .iter()
+                    .position(|(child, _)| *child == Layout::Window(window));
+                match position {
+                    Some(i) if *split_orientation == orientation => {
+                        if children[i].1 < 2 {
+                            for (_, size) in children.iter_mut() {
+                                *size *= 2;
+                            }
+                        }
+                        let half = children[i].1 / 2;
+                        children[i].1 -= half;
+                        children.insert(i + 1, (Layout::Window(new), half));
+                        true
+                    }
+                    _ => children
+                        .iter_mut()
+                        .any(|(child, _)| child.split(window, new, orientation)),
+                }
+            }
+        }
+    }
+
+    /// Removes `window` and gives its space to the window before it, or
+    /// after it for the first one. Returns false if `window` wasn't found or
+    /// is the only window.
+    pub fn remove(&mut self, window: WindowId) -> bool {
+        let children = match self {
+            Layout::Window(_) => return false,
+            Layout::Split { children, .. } => children,
+        };
+        match children
+            .iter()
+            .position(|(child, _)| *child == Layout::Window(window))
+        {
+            Some(i) => {
+                let (_, size) = children.remove(i);
+                children[i.saturating_sub(1)].1 += size;
+            }
+            None => {
+                if !children.iter_mut().any(|(child, _)| child.remove(window)) {
+                    return false;
+                }
+            }
+        }
+        if children.len() == 1 {
+            *self = children.remove(0).0;
+        }
+        true
+    }
+
+    /// Grows `window` by `delta` rows or columns, or shrinks it when
+    /// negative, in the innermost split of `orientation` that holds it. The
+    /// space is taken from or given to the next child, or the previous one
+    /// for the last. Returns whether there was such a split.
+    pub fn resize(&mut self, window: WindowId, orientation: Orientation, delta: isize) -> bool {
+        let (split_orientation, children) = match self {
+            Layout::Window(_) => return false,
+            Layout::Split {
+                orientation,
+                children,
+            } => (*orientation, children),
+        };
+        let i = match children
+            .iter()
+            .position(|(child, _)| child.contains(window))
+        {
+            Some(i) => i,
+            None => return false,
+        };
+        if children[i].0.resize(window, orientation, delta) {
+            return true;
+        }
+        if split_orientation != orientation {
+            return false;
+        }
+        let j = if i + 1 < children.len() { i + 1 } else { i - 1 };
+        let total = children[i].1 + children[j].1;
+        let size = children[i]
+            .1
+            .saturating_add_signed(delta)
+            .clamp(1, total.saturating_sub(1).max(1));
+        children[i].1 = size;
+        children[j].1 = total - size;
+        true
+    }
+
+    /// Gives each window its part of `area`, leaving a cell between
+    /// neighbours for the borders, and remembers the sizes they got.
+    pub fn arrange(
+        &mut self,
+        area: Rect,
+        windows: &mut Vec<(WindowId, Rect)>,
+        borders: &mut Vec<Border>,
+    ) {
+        let (orientation, children) = match self {
+            Layout::Window(id) => {
+                windows.push((*id, area));
+                return;
+            }
+            Layout::Split {
+                orientation,
+                children,
+            } => (*orientation, children),
+        };
+        let length = match orientation {
+            Orientation::Stacked => area.height,
+            Orientation::SideBySide => area.width,
+        };
+        let available = length.saturating_sub(children.len() - 1);
+        let total: usize = children.iter().map(|(_, size)| size).sum();
+        let count = children.len();
+        let mut start = 0;
+        let mut weight = 0;
+        for (i, (child, size)) in children.iter_mut().enumerate() {
+            weight += *size;
+            // Rounding the running total keeps the sizes adding up.
+            let end = (weight * available + total / 2) / total.max(1);
+            // Each child after the first starts after a border.
+            let (part, border) = match orientation {
+                Orientation::Stacked => (
+                    Rect {
+                        y: area.y + start + i,
+                        height: end - start,
+                        ..area
+                    },
+                    Rect {
+                        y: area.y + end + i,
+                        height: 1,
+                        ..area
+                    },
+                ),
+                Orientation::SideBySide => (
+                    Rect {
+                        x: area.x + start + i,
+                        width: end - start,
+                        ..area
+                    },
+                    Rect {
+                        x: area.x + end + i,
+                        width: 1,
+                        ..area
+                    },
+                ),
+            };
+            child.arrange(part, windows, borders);
+            if i + 1 < count {
+                borders.push(Border {
+                    area: border,
+                    orientation,
+                });
+            }
+            *size = (end - start).max(1);
+            start = end;
+        }
+    }
+}
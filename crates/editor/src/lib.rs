@@ -1,16 +1,33 @@
+pub mod diff;
+pub mod display;
+pub mod file;
+pub mod grapheme;
+pub mod hex;
+pub mod keymap;
+pub mod large;
+pub mod layout;
 pub mod location;
+pub mod options;
+pub mod render;
+pub mod status;
+pub mod view;
 
 use anyhow::{format_err, Context as _, Result};
+use file::{DiskChange, DiskState, LineEnding, ReadAs, TextEncoding};
 use handy::typed::{TypedHandle, TypedHandleMap};
-use location::{LineIndex, Movement, Position, Selection};
-use log::trace;
+use hex::HexCursor;
+use large::LargeFile;
+use layout::{Direction, Orientation, Tab};
+use location::{CharIndex, LineIndex, Movement, MovementError, Selection};
+use log::{error, trace};
+use options::Options;
 use ropey::Rope;
 use shlex::split as shlex;
 use std::{
     fmt::Debug,
-    fs::{File, OpenOptions},
     mem::take,
-    path::PathBuf,
+    ops::Range,
+    path::{Path, PathBuf},
 };
 
 pub type WindowId = TypedHandle<WindowData>;
@@ -20,11 +37,12 @@ pub type SelectionId = TypedHandle<Selection>;
 pub struct EditorData {
     pub windows: TypedHandleMap<WindowData>,
     pub buffers: TypedHandleMap<BufferData>,
-    pub open_tabs: Vec<WindowId>,
+    pub open_tabs: Vec<Tab>,
     pub focused_tab: usize,
     pub last_screen_height: Option<u16>,
     pub pending_message: Option<(Importance, String)>,
     pub want_quit: bool,
+    pub options: Options,
 }
 
 pub struct WindowData {
@@ -34,12 +52,50 @@ pub struct WindowData {
     pub primary_selection: SelectionId,
     pub command: String,
     pub top: LineIndex,
+    /// How many rows of the top line are scrolled past when lines wrap.
+    pub top_row: usize,
+    /// The first screen column shown when lines don't wrap.
+    pub left: usize,
+    /// The width and height of the text area the window was last drawn in,
+    /// which the view actions work in.
+    pub view_size: Option<(usize, usize)>,
+    /// Used instead of the selections when the buffer is binary.
+    pub hex_cursor: HexCursor,
 }
 
 pub struct BufferData {
     pub path: Option<PathBuf>,
     pub name: String,
     pub content: Rope,
+    /// Whether the content differs from what was last read from or written
+    /// to disk.
+    pub modified: bool,
+    /// The content as it was last read from or written to disk.
+    pub saved: Rope,
+    /// The line ending and encoding as they were last read from or written
+    /// to disk.
+    pub saved_format: (LineEnding, TextEncoding),
+    /// The edits made by the window action being performed, so that the
+    /// selections of other windows on the buffer can follow them.
+    pub edits: Vec<Edit>,
+    pub disk: Option<DiskState>,
+    /// The line ending used on disk. The content itself always uses `\n`.
+    pub line_ending: LineEnding,
+    pub encoding: TextEncoding,
+    /// The bytes of a binary file, which is shown and edited in the hex view.
+    /// `content` is empty and unused then.
+    pub binary: Option<Vec<u8>>,
+    /// Set for files opened page by page. `content` holds the current page
+    /// and `saved` is empty.
+    pub large: Option<LargeFile>,
+}
+
+/// A change to the content of a buffer, in chars, or in bytes for a binary
+/// buffer.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Edit {
+    Insert { at: usize, len: usize },
+    Remove(Range<usize>),
 }
 
 pub struct NothingLeftToUndo;
@@ -49,18 +105,27 @@ pub enum Mode {
     Normal,
     Insert,
     Append,
-    Goto { selecting: bool },
+    Goto {
+        selecting: bool,
+    },
+    /// After `z`, waiting for the key that says where to scroll the view.
+    View,
+    /// After `ctrl-w`, waiting for the key that says what to do with the
+    /// windows of the tab.
+    Window,
     Command,
 }
 
 #[derive(Debug, Copy, Clone)]
 pub enum Importance {
     Error,
+    Warning,
 }
 
 pub struct Context<'a> {
     pub editor: &'a mut EditorData,
     pub window: WindowId,
+    pub force: bool,
 }
 
 pub struct CommandDesc {
@@ -68,7 +133,6 @@ pub struct CommandDesc {
     pub aliases: &'static [&'static str],
     #[allow(dead_code)]
     pub description: &'static str,
-    #[allow(dead_code)]
     pub required_arguments: usize,
     pub run: fn(cx: Context, args: &[&str]) -> Result<()>,
 }
@@ -87,6 +151,17 @@ pub enum EditorAction {
     Quit,
     PreviousTab,
     NextTab,
+    /// Focuses the tab at a zero-based index.
+    FocusTab(usize),
+    /// Splits the focused window into two on the same buffer and focuses
+    /// the new one.
+    SplitWindow(Orientation),
+    CloseWindow,
+    /// Grows the focused window by rows or columns, or shrinks it.
+    ResizeWindow(Orientation, isize),
+    FocusWindow(Direction),
+    /// Focuses the next window of the tab, or the first after the last.
+    FocusNextWindow,
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -107,6 +182,16 @@ pub enum WindowAction {
     ScrollPageDown,
     ScrollHalfPageUp,
     ScrollHalfPageDown,
+    /// Scrolls the view by rows, leaving the cursor where it is unless it
+    /// would go off screen.
+    ScrollViewUp(usize),
+    ScrollViewDown(usize),
+    /// Scrolls so that the cursor is in the middle of the view.
+    CenterView,
+    /// Scrolls so that the cursor is at the top of the view, or `scrolloff`
+    /// rows below it.
+    ViewCursorAtTop,
+    ViewCursorAtBottom,
     OrderSelections,
     SwitchToMode(Mode),
 }
@@ -124,32 +209,17 @@ impl EditorData {
     pub fn new() -> EditorData {
         let mut windows = TypedHandleMap::new();
         let mut buffers = TypedHandleMap::new();
-        let scratch_buffer = buffers.insert(BufferData {
-            content: Rope::from("\n"),
-            name: String::from("scratch"),
-            path: None,
-        });
-        let mut selections = TypedHandleMap::new();
-        let primary_selection = selections.insert(Selection {
-            start: Position::file_start(),
-            end: Position::file_start(),
-        });
-        let focused_window = windows.insert(WindowData {
-            buffer: scratch_buffer,
-            mode: Mode::Normal,
-            selections,
-            primary_selection,
-            command: String::new(),
-            top: LineIndex::from_one_based(1),
-        });
+        let scratch_buffer = buffers.insert(BufferData::scratch("scratch", Rope::from("\n")));
+        let focused_window = windows.insert(WindowData::new(scratch_buffer));
         EditorData {
             windows,
             buffers,
-            open_tabs: vec![focused_window],
+            open_tabs: vec![Tab::new(focused_window)],
             focused_tab: 0,
             last_screen_height: None,
             pending_message: None,
             want_quit: false,
+            options: Options::default(),
         }
     }
 }
@@ -160,16 +230,469 @@ impl Default for EditorData {
     }
 }
 
+impl EditorData {
+    pub fn focused_window(&self) -> WindowId {
+        self.open_tabs[self.focused_tab].focused
+    }
+}
+
+impl EditorData {
+    /// Adds a tab showing `window` after the focused one and focuses it.
+    pub fn open_tab(&mut self, window: WindowData) {
+        let window_id = self.windows.insert(window);
+        self.focused_tab = (self.focused_tab + 1).min(self.open_tabs.len());
+        self.open_tabs.insert(self.focused_tab, Tab::new(window_id));
+    }
+
+    /// Closes a tab with its windows. The buffers stay open.
+    pub fn close_tab(&mut self, index: usize) -> Result<()> {
+        if index >= self.open_tabs.len() {
+            return Err(format_err!("there is no tab {}", index + 1));
+        } else if self.open_tabs.len() == 1 {
+            return Err(format_err!("cannot close the last window of the last tab"));
+        }
+        let tab = self.open_tabs.remove(index);
+        for window_id in tab.layout.windows() {
+            self.windows.remove(window_id);
+        }
+        if index < self.focused_tab || self.focused_tab == self.open_tabs.len() {
+            self.focused_tab -= 1;
+        }
+        Ok(())
+    }
+
+    /// Moves the focused tab to `index`, counted without it.
+    pub fn move_tab(&mut self, index: usize) {
+        let tab = self.open_tabs.remove(self.focused_tab);
+        self.focused_tab = index.min(self.open_tabs.len());
+        self.open_tabs.insert(self.focused_tab, tab);
+    }
+}
+
+impl EditorData {
+    /// Buffer handles in listing order, as shown by `:buffers`.
+    pub fn buffer_ids(&self) -> Vec<BufferId> {
+        self.buffers.iter_with_handles().map(|(id, _)| id).collect()
+    }
+
+    /// Looks up a buffer by its one-based number in `:buffers`, or by name.
+    pub fn find_buffer(&self, name_or_number: &str) -> Option<BufferId> {
+        if let Ok(number) = name_or_number.parse::<usize>() {
+            if let Some(id) = number
+                .checked_sub(1)
+                .and_then(|i| self.buffer_ids().get(i).copied())
+            {
+                return Some(id);
+            }
+        }
+        self.buffers
+            .iter_with_handles()
+            .find(|(_, buffer)| buffer.name == name_or_number)
+            .map(|(id, _)| id)
+    }
+
+    pub fn find_buffer_by_path(&self, path: &Path) -> Option<BufferId> {
+        self.buffers
+            .iter_with_handles()
+            .find(|(_, buffer)| buffer.path.as_deref() == Some(path))
+            .map(|(id, _)| id)
+    }
+
+    /// Removes a buffer, pointing every window that showed it at another
+    /// buffer. A new scratch buffer is created if none are left.
+    pub fn remove_buffer(&mut self, buffer_id: BufferId) {
+        self.buffers.remove(buffer_id);
+        let replacement = self
+            .buffers
+            .iter_with_handles()
+            .find(|(_, buffer)| !buffer.is_buffer_list())
+            .map(|(id, _)| id);
+        let replacement = match replacement {
+            Some(id) => id,
+            None => self
+                .buffers
+                .insert(BufferData::scratch("scratch", Rope::from("\n"))),
+        };
+        for window in self.windows.iter_mut() {
+            if window.buffer == buffer_id {
+                window.set_buffer(replacement);
+            }
+        }
+    }
+}
+
+impl EditorData {
+    /// Keeps the selections of every window showing `buffer_id` inside the
+    /// buffer after its content was replaced.
+    pub fn clamp_selections(&mut self, buffer_id: BufferId) {
+        let content = &self.buffers[buffer_id].content;
+        for window in self.windows.iter_mut() {
+            if window.buffer == buffer_id {
+                for selection in window.selections.iter_mut() {
+                    selection.clamp_to(content);
+                }
+            }
+        }
+    }
+}
+
+impl EditorData {
+    /// Shows another page of a large file in the buffer, keeping the edits
+    /// made to the current one.
+    pub fn switch_page(&mut self, buffer_id: BufferId, page: u64) -> Result<()> {
+        let buffer = &mut self.buffers[buffer_id];
+        let large = buffer
+            .large
+            .as_mut()
+            .context("buffer is not a large file")?;
+        buffer.content = large.switch_page(take(&mut buffer.content), page)?;
+        for window in self.windows.iter_mut() {
+            if window.buffer == buffer_id {
+                window.top = LineIndex::from_one_based(1);
+                window.top_row = 0;
+                window.left = 0;
+            }
+        }
+        self.clamp_selections(buffer_id);
+        Ok(())
+    }
+}
+
+impl EditorData {
+    /// Reloads a buffer from disk, and moves the selections of the windows
+    /// showing it along with the lines they were on.
+    pub fn reload_buffer(&mut self, buffer_id: BufferId, read_as: ReadAs) -> Result<()> {
+        let buffer = &mut self.buffers[buffer_id];
+        let old = buffer.content.clone();
+        buffer.reload(read_as)?;
+        if buffer.large.is_some() {
+            self.clamp_selections(buffer_id);
+            return Ok(());
+        }
+        if let Some(bytes) = &buffer.binary {
+            for window in self.windows.iter_mut() {
+                if window.buffer == buffer_id {
+                    let offset = window.hex_cursor.offset.min(bytes.len());
+                    window.hex_cursor = HexCursor {
+                        offset,
+                        low_nibble: false,
+                    };
+                }
+            }
+            return Ok(());
+        }
+        let hunks = diff::diff(
+            &old.lines().collect::<Vec<_>>(),
+            &buffer.content.lines().collect::<Vec<_>>(),
+        );
+        for window in self.windows.iter_mut() {
+            if window.buffer == buffer_id {
+                for selection in window.selections.iter_mut() {
+                    for char_idx in [&mut selection.start, &mut selection.end] {
+                        let mut position = char_idx.position(&old);
+                        position.line = LineIndex::from_zero_based(diff::map_line(
+                            &hunks,
+                            position.line.zero_based(),
+                        ));
+                        *char_idx = CharIndex::from_position(&buffer.content, position);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl WindowData {
+    pub fn new(buffer: BufferId) -> WindowData {
+        let mut selections = TypedHandleMap::new();
+        let primary_selection = selections.insert(Selection::new(CharIndex(0)));
+        WindowData {
+            buffer,
+            mode: Mode::Normal,
+            selections,
+            primary_selection,
+            command: String::new(),
+            top: LineIndex::from_one_based(1),
+            top_row: 0,
+            left: 0,
+            view_size: None,
+            hex_cursor: HexCursor::default(),
+        }
+    }
+
+    /// A new window on the same buffer with the same selections and view,
+    /// which change independently from here on.
+    pub fn split(&self) -> WindowData {
+        let mut selections = TypedHandleMap::new();
+        let mut primary_selection = None;
+        for (id, selection) in self.selections.iter_with_handles() {
+            let new = selections.insert(*selection);
+            if id == self.primary_selection {
+                primary_selection = Some(new);
+            }
+        }
+        WindowData {
+            buffer: self.buffer,
+            mode: Mode::Normal,
+            selections,
+            primary_selection: primary_selection.expect("window has no primary selection"),
+            command: String::new(),
+            top: self.top,
+            top_row: self.top_row,
+            left: self.left,
+            view_size: self.view_size,
+            hex_cursor: self.hex_cursor,
+        }
+    }
+
+    /// Shows another buffer in this window, resetting the selections.
+    pub fn set_buffer(&mut self, buffer: BufferId) {
+        let mode = self.mode;
+        let command = take(&mut self.command);
+        *self = WindowData {
+            mode,
+            command,
+            ..WindowData::new(buffer)
+        };
+    }
+
+    /// The char ranges covered by selections within `visible`, sorted and
+    /// with overlapping ones merged, so that drawing can walk them alongside
+    /// the text instead of testing every selection for every char.
+    pub fn selection_ranges(&self, rope: &Rope, visible: Range<usize>) -> Vec<Range<usize>> {
+        let mut ranges: Vec<Range<usize>> = self
+            .selections
+            .iter()
+            .filter_map(|selection| {
+                let mut selection = selection.ordered();
+                // Most selections are off screen, so skip them before doing
+                // any grapheme work.
+                if selection.start.0 >= visible.end || selection.end.0 < visible.start {
+                    return None;
+                }
+                selection.clamp_to(rope);
+                let range = selection.range_of(rope);
+                let range = range.start.max(visible.start)..range.end.min(visible.end);
+                (range.start < range.end).then_some(range)
+            })
+            .collect();
+        ranges.sort_unstable_by_key(|range| range.start);
+        let mut merged: Vec<Range<usize>> = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            match merged.last_mut() {
+                Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+                _ => merged.push(range),
+            }
+        }
+        merged
+    }
+}
+
+/// The name of the scratch buffer `:buffers` shows the list in.
+const BUFFER_LIST_NAME: &str = "[buffers]";
+
+impl BufferData {
+    /// Whether this is the buffer `:buffers` shows the list in.
+    fn is_buffer_list(&self) -> bool {
+        self.name == BUFFER_LIST_NAME && self.path.is_none()
+    }
+
+    /// Whether the buffer would write something other than what was last
+    /// read from or written to disk. The pages of a large file aren't
+    /// compared, so an edited one stays modified.
+    fn differs_from_saved(&self) -> bool {
+        self.large.is_some()
+            || (self.line_ending, self.encoding) != self.saved_format
+            || self.content != self.saved
+    }
+
+    pub fn scratch(name: &str, content: Rope) -> BufferData {
+        BufferData {
+            path: None,
+            name: String::from(name),
+            saved: content.clone(),
+            saved_format: (LineEnding::Lf, TextEncoding::UTF_8),
+            edits: Vec::new(),
+            content,
+            modified: false,
+            disk: None,
+            line_ending: LineEnding::Lf,
+            encoding: TextEncoding::UTF_8,
+            binary: None,
+            large: None,
+        }
+    }
+
+    pub fn open(name: String, path: PathBuf, options: &Options) -> Result<BufferData> {
+        if std::fs::metadata(&path)?.len() >= options.large_file_threshold {
+            if let Some(encoding) = file::pageable_encoding(&path)? {
+                return BufferData::open_large(name, path, encoding);
+            }
+        }
+        let contents = file::read(&path, ReadAs::Detect)?;
+        Ok(BufferData {
+            path: Some(path),
+            name,
+            saved: contents.text.clone(),
+            saved_format: (contents.line_ending, contents.encoding),
+            edits: Vec::new(),
+            content: contents.text,
+            modified: false,
+            disk: Some(contents.disk),
+            line_ending: contents.line_ending,
+            encoding: contents.encoding,
+            binary: contents.binary,
+            large: None,
+        })
+    }
+
+    fn open_large(name: String, path: PathBuf, encoding: TextEncoding) -> Result<BufferData> {
+        let large = LargeFile::open(&path, encoding)?;
+        let content = large.read_page(0)?;
+        Ok(BufferData {
+            disk: Some(DiskState::stat(&path)?),
+            path: Some(path),
+            name,
+            content,
+            saved: Rope::new(),
+            saved_format: (large.line_ending, encoding),
+            edits: Vec::new(),
+            modified: false,
+            line_ending: large.line_ending,
+            encoding,
+            binary: None,
+            large: Some(large),
+        })
+    }
+
+    pub fn write_to(&mut self, path: &Path, options: &Options) -> Result<()> {
+        let disk = file::write_atomic(path, options.backup, |writer| {
+            match (&self.binary, &self.large) {
+                (Some(bytes), _) => writer.write_all(bytes),
+                (None, Some(large)) => large.write_to(&self.content, writer),
+                (None, None) => {
+                    file::write_text(&self.content, self.line_ending, self.encoding, writer)
+                }
+            }
+        })?;
+        if let Some(large) = &mut self.large {
+            // The old file is gone, so the pages are found again in the new
+            // one.
+            let page = large.page;
+            *large = LargeFile::open(path, large.encoding)?;
+            large.page = page.min(large.page_count() - 1);
+            self.content = large.read_page(large.page)?;
+        } else {
+            self.saved = self.content.clone();
+        }
+        self.saved_format = (self.line_ending, self.encoding);
+        self.disk = Some(disk);
+        self.modified = false;
+        Ok(())
+    }
+
+    /// Writes the buffer back to its own path. Unless `force` is set, this
+    /// fails if the file was changed on disk since it was last read.
+    pub fn save(&mut self, options: &Options, force: bool) -> Result<()> {
+        let path = self.path.clone().context("cannot save a scratch buffer")?;
+        if !force && self.check_disk()? == DiskChange::Modified {
+            return Err(format_err!("{}", changed_on_disk_message(&self.name)));
+        }
+        self.write_to(&path, options)
+    }
+
+    pub fn check_disk(&mut self) -> Result<DiskChange> {
+        if let (Some(path), Some(disk), Some(_)) = (&self.path, &self.disk, &self.large) {
+            return match DiskState::stat(path) {
+                Ok(state) if (state.modified, state.len) == (disk.modified, disk.len) => {
+                    Ok(DiskChange::Unchanged)
+                }
+                Ok(_) => Ok(DiskChange::Modified),
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(DiskChange::Deleted),
+                Err(err) => Err(err.into()),
+            };
+        }
+        match (&self.path, &mut self.disk) {
+            (Some(path), Some(disk)) => Ok(disk.check(path)?),
+            _ => Ok(DiskChange::Unchanged),
+        }
+    }
+
+    /// Replaces the content with what is currently on disk.
+    pub fn reload(&mut self, read_as: ReadAs) -> Result<()> {
+        let path = self
+            .path
+            .as_ref()
+            .context("cannot reload a scratch buffer")?;
+        if self.large.is_some() {
+            let path = path.clone();
+            *self = BufferData::open_large(take(&mut self.name), path, self.encoding)?;
+            return Ok(());
+        }
+        let contents = file::read(path, read_as)?;
+        self.saved = contents.text.clone();
+        self.content = contents.text;
+        self.disk = Some(contents.disk);
+        self.line_ending = contents.line_ending;
+        self.encoding = contents.encoding;
+        self.binary = contents.binary;
+        self.saved_format = (self.line_ending, self.encoding);
+        self.modified = false;
+        Ok(())
+    }
+
+    /// Three-way merges the changes made on disk into the content, using the
+    /// last saved version as the base. Returns the number of conflicts.
+    pub fn merge_from_disk(&mut self) -> Result<usize> {
+        let path = self
+            .path
+            .as_ref()
+            .context("cannot merge a scratch buffer")?;
+        if self.binary.is_some() {
+            return Err(format_err!("cannot merge a binary buffer"));
+        }
+        if self.large.is_some() {
+            return Err(format_err!("cannot merge a large file"));
+        }
+        let theirs = file::read(path, ReadAs::Text(self.encoding))?;
+        let lines = |rope: &Rope| rope.lines().map(String::from).collect::<Vec<_>>();
+        let merge = diff::merge(
+            &lines(&self.saved),
+            &lines(&self.content),
+            &lines(&theirs.text),
+        );
+        self.content = Rope::from(merge.lines.concat());
+        self.saved = theirs.text;
+        self.saved_format = (theirs.line_ending, theirs.encoding);
+        self.disk = Some(theirs.disk);
+        self.modified = true;
+        Ok(merge.conflicts)
+    }
+}
+
 pub fn run_command(state: &mut EditorData, args: &[&str]) -> Result<()> {
     let name = args.first().copied().context("no command given")?;
+    let (name, force) = match name.strip_suffix('!') {
+        Some(name) => (name, true),
+        None => (name, false),
+    };
     let cmd = COMMANDS
         .iter()
         .find(|desc| desc.name == name || desc.aliases.contains(&name))
         .ok_or_else(|| format_err!("command '{}' doesn't exist", name))?;
+    if args.len() - 1 < cmd.required_arguments {
+        return Err(format_err!(
+            "command '{}' requires {} argument(s)",
+            cmd.name,
+            cmd.required_arguments
+        ));
+    }
     (cmd.run)(
         Context {
-            window: state.open_tabs[state.focused_tab],
+            window: state.focused_window(),
             editor: state,
+            force,
         },
         &args[1..],
     )
@@ -181,11 +704,60 @@ pub fn perform_editor_action(state: &mut EditorData, action: EditorAction) -> Re
             state.want_quit = true;
         }
         EditorAction::PreviousTab => {
-            state.focused_tab = (state.focused_tab - 1) % state.open_tabs.len();
+            let len = state.open_tabs.len();
+            state.focused_tab = (state.focused_tab + len - 1) % len;
         }
         EditorAction::NextTab => {
             state.focused_tab = (state.focused_tab + 1) % state.open_tabs.len();
         }
+        EditorAction::FocusTab(tab) => {
+            if tab >= state.open_tabs.len() {
+                return Err(format_err!("there is no tab {}", tab + 1));
+            }
+            state.focused_tab = tab;
+        }
+        EditorAction::SplitWindow(orientation) => {
+            let window = state.windows[state.focused_window()].split();
+            let new = state.windows.insert(window);
+            let tab = &mut state.open_tabs[state.focused_tab];
+            tab.layout.split(tab.focused, new, orientation);
+            tab.focused = new;
+        }
+        EditorAction::CloseWindow => {
+            let tab = &mut state.open_tabs[state.focused_tab];
+            let windows = tab.layout.windows();
+            let i = windows
+                .iter()
+                .position(|&id| id == tab.focused)
+                .context("focused window is not in the tab")?;
+            if windows.len() == 1 {
+                // The last window of a tab goes with it.
+                return state.close_tab(state.focused_tab);
+            }
+            tab.layout.remove(tab.focused);
+            state.windows.remove(tab.focused);
+            // The window that took over the space gets the focus.
+            tab.focused = tab.layout.windows()[i.saturating_sub(1)];
+        }
+        EditorAction::ResizeWindow(orientation, delta) => {
+            let tab = &mut state.open_tabs[state.focused_tab];
+            tab.layout.resize(tab.focused, orientation, delta);
+        }
+        EditorAction::FocusWindow(direction) => {
+            let tab = &mut state.open_tabs[state.focused_tab];
+            if let Some(window) = tab.neighbour(direction) {
+                tab.focused = window;
+            }
+        }
+        EditorAction::FocusNextWindow => {
+            let tab = &mut state.open_tabs[state.focused_tab];
+            let windows = tab.layout.windows();
+            let i = windows
+                .iter()
+                .position(|&id| id == tab.focused)
+                .unwrap_or(0);
+            tab.focused = windows[(i + 1) % windows.len()];
+        }
     }
     Ok(())
 }
@@ -203,27 +775,78 @@ pub fn perform_window_action(
     buffer: &mut BufferData,
     action: WindowAction,
     last_screen_height: Option<u16>,
+    options: &Options,
 ) -> Result<()> {
-    for selection in window.selections.iter_mut() {
+    if let WindowAction::ScrollViewUp(_)
+    | WindowAction::ScrollViewDown(_)
+    | WindowAction::CenterView
+    | WindowAction::ViewCursorAtTop
+    | WindowAction::ViewCursorAtBottom = action
+    {
+        return window.perform_view_action(buffer, options, action);
+    }
+    let tab_width = options.tab_width;
+    let ids: Vec<SelectionId> = window
+        .selections
+        .iter_with_handles()
+        .map(|(id, _)| id)
+        .collect();
+    for &id in &ids {
+        let selection = &mut window.selections[id];
         match action {
-            WindowAction::InsertAtSelectionStart(c) => {
-                selection.start.insert_char(buffer, c);
-            }
-            WindowAction::InsertAtSelectionEnd(c) => {
-                selection.end.insert_char(buffer, c);
+            WindowAction::InsertAtSelectionStart(c) | WindowAction::InsertAtSelectionEnd(c) => {
+                let char_idx = match action {
+                    WindowAction::InsertAtSelectionStart(_) => &mut selection.start,
+                    _ => &mut selection.end,
+                };
+                char_idx.clamp_to(&buffer.content);
+                let at = char_idx.0;
+                char_idx.insert_char(buffer, c);
+                selection.desired_column = None;
+                buffer.edits.push(Edit::Insert { at, len: 1 });
+                // The other selections stay on the text they were on.
+                for &other in ids.iter().filter(|&&other| other != id) {
+                    window.selections[other].shift_for_insert(at, 1);
+                }
+                // A combining character joins the cluster before it.
+                for selection in window.selections.iter_mut() {
+                    selection.snap_to_graphemes(&buffer.content);
+                }
             }
             WindowAction::Delete => {
-                selection.remove_from(buffer);
+                let removed = selection.remove_from(buffer);
+                selection.desired_column = None;
+                buffer.edits.push(Edit::Remove(removed.clone()));
+                for &other in ids.iter().filter(|&&other| other != id) {
+                    let selection = &mut window.selections[other];
+                    selection.shift_for_remove(removed.clone());
+                    selection.clamp_to(&buffer.content);
+                }
             }
             WindowAction::Move(movement) => {
-                selection.end.move_to(&buffer.content, movement)?;
+                selection.end.move_visually(
+                    &buffer.content,
+                    movement,
+                    tab_width,
+                    &mut selection.desired_column,
+                )?;
                 selection.start = selection.end;
             }
             WindowAction::ShiftStart(movement) => {
-                selection.start.move_to(&buffer.content, movement)?;
+                selection.start.move_visually(
+                    &buffer.content,
+                    movement,
+                    tab_width,
+                    &mut selection.desired_column,
+                )?;
             }
             WindowAction::ShiftEnd(movement) => {
-                selection.end.move_to(&buffer.content, movement)?;
+                selection.end.move_visually(
+                    &buffer.content,
+                    movement,
+                    tab_width,
+                    &mut selection.desired_column,
+                )?;
             }
             WindowAction::ScrollPageUp
             | WindowAction::ScrollPageDown
@@ -238,10 +861,20 @@ pub fn perform_window_action(
                         WindowAction::ScrollHalfPageDown => Movement::Down(height / 2),
                         _ => unreachable!(),
                     };
-                    selection.end.move_to(&buffer.content, movement)?;
+                    selection.end.move_visually(
+                        &buffer.content,
+                        movement,
+                        tab_width,
+                        &mut selection.desired_column,
+                    )?;
                     selection.start = selection.end;
                 }
             }
+            WindowAction::ScrollViewUp(_)
+            | WindowAction::ScrollViewDown(_)
+            | WindowAction::CenterView
+            | WindowAction::ViewCursorAtTop
+            | WindowAction::ViewCursorAtBottom => unreachable!(),
             WindowAction::OrderSelections => {
                 selection.order();
             }
@@ -250,21 +883,21 @@ pub fn perform_window_action(
             }
         }
     }
+    if !buffer.edits.is_empty() {
+        buffer.modified = buffer.differs_from_saved();
+    }
     Ok(())
 }
 
 pub fn perform_command_action(state: &mut EditorData, action: CommandAction) -> Result<()> {
+    let window_id = state.focused_window();
     match action {
         CommandAction::Character(c) => {
-            state.windows[state.open_tabs[state.focused_tab]]
-                .command
-                .push(c);
+            state.windows[window_id].command.push(c);
             Ok(())
         }
         CommandAction::Clear => {
-            state.windows[state.open_tabs[state.focused_tab]]
-                .command
-                .clear();
+            state.windows[window_id].command.clear();
             Ok(())
         }
         CommandAction::Tab => {
@@ -272,8 +905,8 @@ pub fn perform_command_action(state: &mut EditorData, action: CommandAction) ->
             Ok(())
         }
         CommandAction::Return => {
-            let command = take(&mut state.windows[state.open_tabs[state.focused_tab]].command);
-            state.windows[state.open_tabs[state.focused_tab]].mode = Mode::Normal;
+            let command = take(&mut state.windows[window_id].command);
+            state.windows[window_id].mode = Mode::Normal;
             let command = shlex(&command)
                 .ok_or_else(|| format_err!("failed to parse command '{}'", command))?;
             trace!("command: {:?}", command);
@@ -282,13 +915,9 @@ pub fn perform_command_action(state: &mut EditorData, action: CommandAction) ->
             Ok(())
         }
         CommandAction::Backspace => {
-            if state.windows[state.open_tabs[state.focused_tab]]
-                .command
-                .pop()
-                .is_none()
-            {
+            if state.windows[window_id].command.pop().is_none() {
                 let mode: Mode = Mode::Normal;
-                state.windows[state.open_tabs[state.focused_tab]].mode = mode;
+                state.windows[window_id].mode = mode;
             }
             Ok(())
         }
@@ -300,26 +929,219 @@ pub fn perform_action(state: &mut EditorData, action: Action) -> Result<()> {
         Action::Editor(editor_action) => perform_editor_action(state, editor_action),
         Action::Buffer(buffer_action) => perform_buffer_action(state, buffer_action),
         Action::Window(window_action) => {
-            let window_id = state.open_tabs[state.focused_tab];
+            let window_id = state.focused_window();
             let window = &mut state.windows[window_id];
             let buffer = &mut state.buffers[window.buffer];
-            perform_window_action(window, buffer, window_action, state.last_screen_height)
+            let result = if buffer.binary.is_some() {
+                hex::perform_window_action(window, buffer, window_action, state.last_screen_height)
+            } else if buffer.large.is_some() {
+                let result = perform_window_action(
+                    window,
+                    buffer,
+                    window_action,
+                    state.last_screen_height,
+                    &state.options,
+                );
+                follow_large_file(state, window_id, window_action, result)
+            } else {
+                perform_window_action(
+                    window,
+                    buffer,
+                    window_action,
+                    state.last_screen_height,
+                    &state.options,
+                )
+            };
+            follow_edits(state, window_id);
+            result
         }
         Action::Command(command_action) => perform_command_action(state, command_action),
     }
 }
 
+/// Keeps the selections of the other windows on the focused window's buffer
+/// on the text they were on, after edits made through the focused window.
+/// In a binary buffer their hex cursors are kept on their bytes instead.
+fn follow_edits(state: &mut EditorData, window_id: WindowId) {
+    let buffer_id = state.windows[window_id].buffer;
+    let buffer = &mut state.buffers[buffer_id];
+    let edits = take(&mut buffer.edits);
+    if edits.is_empty() {
+        return;
+    }
+    let others: Vec<WindowId> = state
+        .windows
+        .iter_with_handles()
+        .filter(|&(id, window)| id != window_id && window.buffer == buffer_id)
+        .map(|(id, _)| id)
+        .collect();
+    for id in others {
+        let window = &mut state.windows[id];
+        if let Some(bytes) = &buffer.binary {
+            for edit in &edits {
+                window.hex_cursor.follow_edit(edit);
+            }
+            window.hex_cursor.offset = window.hex_cursor.offset.min(bytes.len());
+            continue;
+        }
+        for selection in window.selections.iter_mut() {
+            for edit in &edits {
+                match edit {
+                    Edit::Insert { at, len } => selection.shift_for_insert(*at, *len),
+                    Edit::Remove(range) => selection.shift_for_remove(range.clone()),
+                }
+            }
+            selection.clamp_to(&buffer.content);
+            selection.snap_to_graphemes(&buffer.content);
+        }
+    }
+}
+
+/// Moves to the neighbouring page of a large file when a movement runs off
+/// the current one, and to the first or last page for file starts and ends.
+fn follow_large_file(
+    state: &mut EditorData,
+    window_id: WindowId,
+    action: WindowAction,
+    result: Result<()>,
+) -> Result<()> {
+    let buffer_id = state.windows[window_id].buffer;
+    let large = state.buffers[buffer_id]
+        .large
+        .as_ref()
+        .context("buffer is not a large file")?;
+    let (page, movement) = match (action, &result) {
+        (WindowAction::Move(Movement::FileStart), Ok(())) if large.page != 0 => {
+            (0, Movement::FileStart)
+        }
+        (WindowAction::Move(Movement::FileEnd), Ok(())) if !large.is_last_page() => {
+            (large.page_count() - 1, Movement::FileEnd)
+        }
+        (WindowAction::Move(_), Err(err)) => match err.downcast_ref::<MovementError>() {
+            Some(MovementError::NoPrevLine) if large.page != 0 => {
+                (large.page - 1, Movement::FileEnd)
+            }
+            Some(MovementError::NoNextLine) if !large.is_last_page() => {
+                (large.page + 1, Movement::FileStart)
+            }
+            _ => return result,
+        },
+        _ => return result,
+    };
+    state.switch_page(buffer_id, page)?;
+    let window = &mut state.windows[window_id];
+    let buffer = &mut state.buffers[buffer_id];
+    perform_window_action(
+        window,
+        buffer,
+        WindowAction::Move(movement),
+        state.last_screen_height,
+        &state.options,
+    )
+}
+
 pub fn show_message(state: &mut EditorData, importance: Importance, message: String) {
     state.pending_message = Some((importance, message));
 }
 
+/// Warns about buffers whose files were changed or deleted by another
+/// program. Frontends call this when they regain focus.
+pub fn check_disk_changes(state: &mut EditorData) {
+    let mut message = None;
+    for buffer in state.buffers.iter_mut() {
+        match buffer.check_disk() {
+            Ok(DiskChange::Unchanged) => {}
+            Ok(DiskChange::Modified) => {
+                message = Some(changed_on_disk_message(&buffer.name));
+            }
+            Ok(DiskChange::Deleted) => {
+                message = Some(format!("'{}' was deleted from disk", buffer.name));
+            }
+            Err(err) => error!("failed to check '{}': {}", buffer.name, err),
+        }
+    }
+    if let Some(message) = message {
+        show_message(state, Importance::Warning, message);
+    }
+}
+
+/// Reacts to another program changing the file at `path`. A buffer showing
+/// it is reloaded in place if it has no unsaved changes, otherwise the
+/// conflict is reported.
+pub fn handle_file_change(state: &mut EditorData, path: &Path) {
+    let buffer_id = match state.find_buffer_by_path(path) {
+        Some(buffer_id) => buffer_id,
+        None => return,
+    };
+    let buffer = &mut state.buffers[buffer_id];
+    let result = match buffer.check_disk() {
+        Ok(DiskChange::Unchanged) => Ok(()),
+        Ok(DiskChange::Modified) if !buffer.modified => {
+            state.reload_buffer(buffer_id, ReadAs::Detect)
+        }
+        Ok(DiskChange::Modified) => {
+            let message = changed_on_disk_message(&buffer.name);
+            show_message(state, Importance::Warning, message);
+            Ok(())
+        }
+        Ok(DiskChange::Deleted) => {
+            let message = format!("'{}' was deleted from disk", buffer.name);
+            show_message(state, Importance::Warning, message);
+            Ok(())
+        }
+        Err(err) => Err(err),
+    };
+    if let Err(err) = result {
+        show_message(state, Importance::Error, format!("{:#}", err));
+    }
+}
+
+fn changed_on_disk_message(name: &str) -> String {
+    format!(
+        "'{}' changed on disk; use :reload, :merge or :write! to overwrite it",
+        name
+    )
+}
+
 const COMMANDS: &[CommandDesc] = &[
     CommandDesc {
         name: "quit",
         aliases: &["q"],
         description: "quit the editor",
         required_arguments: 0,
-        run: |cx, _args| perform_editor_action(cx.editor, EditorAction::Quit),
+        run: |cx, _args| quit(cx),
+    },
+    CommandDesc {
+        name: "quitall",
+        aliases: &["qa"],
+        description: "quit the editor, closing every buffer",
+        required_arguments: 0,
+        run: |cx, _args| quit(cx),
+    },
+    CommandDesc {
+        name: "wq",
+        aliases: &[],
+        description: "write the current buffer and quit the editor",
+        required_arguments: 0,
+        run: |cx, _args| {
+            let buffer_id = cx.editor.windows[cx.window].buffer;
+            cx.editor.buffers[buffer_id].save(&cx.editor.options, cx.force)?;
+            quit(cx)
+        },
+    },
+    CommandDesc {
+        name: "wall",
+        aliases: &["wa"],
+        description: "write every modified buffer to disk",
+        required_arguments: 0,
+        run: |cx, _args| {
+            for buffer in cx.editor.buffers.iter_mut() {
+                if buffer.modified && buffer.path.is_some() {
+                    buffer.save(&cx.editor.options, cx.force)?;
+                }
+            }
+            Ok(())
+        },
     },
     CommandDesc {
         name: "open",
@@ -327,30 +1149,24 @@ const COMMANDS: &[CommandDesc] = &[
         description: "open a file",
         required_arguments: 1,
         run: |cx, args| {
-            let name = String::from(args[0]);
-            let path = PathBuf::from(&name).canonicalize()?;
-            let reader = File::open(&path)?;
-            let buffer = BufferData {
-                path: Some(path),
-                name,
-                content: Rope::from_reader(reader)?,
-            };
-            let buffer_id = cx.editor.buffers.insert(buffer);
-            let mut selections = TypedHandleMap::new();
-            let selection_id = selections.insert(Selection {
-                start: Position::file_start(),
-                end: Position::file_start(),
+            let buffer_id = open_buffer(cx.editor, args[0])?;
+            let windows = &cx.editor.windows;
+            let shown = cx.editor.open_tabs.iter().enumerate().find_map(|(i, tab)| {
+                tab.layout
+                    .windows()
+                    .into_iter()
+                    .find(|&window_id| windows[window_id].buffer == buffer_id)
+                    .map(|window_id| (i, window_id))
             });
-            let window = WindowData {
-                buffer: buffer_id,
-                command: String::new(),
-                mode: Mode::Normal,
-                selections,
-                primary_selection: selection_id,
-                top: LineIndex::from_one_based(1),
-            };
+            if let Some((i, window_id)) = shown {
+                cx.editor.open_tabs[i].focused = window_id;
+                cx.editor.focused_tab = i;
+                return Ok(());
+            }
+            let window = WindowData::new(buffer_id);
             let focused_tab = cx.editor.open_tabs.len();
-            cx.editor.open_tabs.push(cx.editor.windows.insert(window));
+            let window_id = cx.editor.windows.insert(window);
+            cx.editor.open_tabs.push(Tab::new(window_id));
             cx.editor.focused_tab = focused_tab;
             Ok(())
         },
@@ -361,14 +1177,492 @@ const COMMANDS: &[CommandDesc] = &[
         description: "write the current buffer contents to disk",
         required_arguments: 0,
         run: |cx, _args| {
-            let buffer = &cx.editor.buffers[cx.editor.windows[cx.window].buffer];
-            let path = buffer
-                .path
-                .as_ref()
-                .context("cannot save a scratch buffer")?;
-            let mut file = OpenOptions::new().write(true).truncate(true).open(path)?;
-            buffer.content.write_to(&mut file)?;
+            let buffer_id = cx.editor.windows[cx.window].buffer;
+            cx.editor.buffers[buffer_id].save(&cx.editor.options, cx.force)
+        },
+    },
+    CommandDesc {
+        name: "reload",
+        aliases: &[],
+        description: "discard the current buffer contents and read the file again",
+        required_arguments: 0,
+        run: |cx, _args| {
+            let buffer_id = cx.editor.windows[cx.window].buffer;
+            let buffer = &mut cx.editor.buffers[buffer_id];
+            if buffer.modified && !cx.force {
+                return Err(format_err!(
+                    "buffer '{}' has unsaved changes (add ! to override)",
+                    buffer.name
+                ));
+            }
+            cx.editor.reload_buffer(buffer_id, ReadAs::Detect)
+        },
+    },
+    CommandDesc {
+        name: "reopen",
+        aliases: &[],
+        description: "read the current buffer's file again, as ++enc=<encoding> or ++bin",
+        required_arguments: 0,
+        run: |cx, args| {
+            let mut read_as = ReadAs::Detect;
+            for arg in args {
+                read_as = match (*arg, arg.strip_prefix("++enc=")) {
+                    (_, Some(label)) => ReadAs::Text(TextEncoding::from_label(label)?),
+                    ("++bin", None) => ReadAs::Binary,
+                    _ => return Err(format_err!("unknown argument '{}'", arg)),
+                };
+            }
+            let buffer_id = cx.editor.windows[cx.window].buffer;
+            let buffer = &cx.editor.buffers[buffer_id];
+            if buffer.modified && !cx.force {
+                return Err(format_err!(
+                    "buffer '{}' has unsaved changes (add ! to override)",
+                    buffer.name
+                ));
+            }
+            cx.editor.reload_buffer(buffer_id, read_as)
+        },
+    },
+    CommandDesc {
+        name: "merge",
+        aliases: &[],
+        description: "merge changes made on disk into the current buffer",
+        required_arguments: 0,
+        run: |cx, _args| {
+            let buffer_id = cx.editor.windows[cx.window].buffer;
+            let conflicts = cx.editor.buffers[buffer_id].merge_from_disk()?;
+            cx.editor.clamp_selections(buffer_id);
+            if conflicts > 0 {
+                show_message(
+                    cx.editor,
+                    Importance::Warning,
+                    format!("merged with {} conflict(s)", conflicts),
+                );
+            }
+            Ok(())
+        },
+    },
+    CommandDesc {
+        name: "saveas",
+        aliases: &["sav"],
+        description: "write the current buffer to a new path and switch to it",
+        required_arguments: 1,
+        run: |cx, args| {
+            let path = Path::new(args[0]);
+            let buffer_id = cx.editor.windows[cx.window].buffer;
+            if let Some(other) = path
+                .canonicalize()
+                .ok()
+                .and_then(|path| cx.editor.find_buffer_by_path(&path))
+                .filter(|&other| other != buffer_id)
+            {
+                return Err(format_err!(
+                    "'{}' is already open in buffer '{}'",
+                    path.display(),
+                    cx.editor.buffers[other].name
+                ));
+            }
+            if path.exists() && !cx.force {
+                return Err(format_err!(
+                    "'{}' already exists (add ! to override)",
+                    path.display()
+                ));
+            }
+            let buffer = &mut cx.editor.buffers[buffer_id];
+            buffer.write_to(path, &cx.editor.options)?;
+            buffer.path = Some(PathBuf::from(args[0]).canonicalize()?);
+            buffer.name = String::from(args[0]);
+            Ok(())
+        },
+    },
+    CommandDesc {
+        name: "rename",
+        aliases: &[],
+        description: "rename the current buffer",
+        required_arguments: 1,
+        run: |cx, args| {
+            let name = String::from(args[0]);
+            if cx.editor.find_buffer(&name).is_some() {
+                return Err(format_err!("a buffer named '{}' already exists", name));
+            }
+            cx.editor.buffers[cx.editor.windows[cx.window].buffer].name = name;
+            Ok(())
+        },
+    },
+    CommandDesc {
+        name: "set",
+        aliases: &["se"],
+        description: "change editor options",
+        required_arguments: 1,
+        run: |cx, args| {
+            let buffer_id = cx.editor.windows[cx.window].buffer;
+            for arg in args {
+                set_option(cx.editor, buffer_id, arg)?;
+            }
+            Ok(())
+        },
+    },
+    CommandDesc {
+        name: "split",
+        aliases: &["sp"],
+        description: "split the current window into two above one another",
+        required_arguments: 0,
+        run: |cx, _args| {
+            perform_editor_action(cx.editor, EditorAction::SplitWindow(Orientation::Stacked))
+        },
+    },
+    CommandDesc {
+        name: "vsplit",
+        aliases: &["vs"],
+        description: "split the current window into two side by side",
+        required_arguments: 0,
+        run: |cx, _args| {
+            perform_editor_action(
+                cx.editor,
+                EditorAction::SplitWindow(Orientation::SideBySide),
+            )
+        },
+    },
+    CommandDesc {
+        name: "close",
+        aliases: &["clo"],
+        description: "close the current window",
+        required_arguments: 0,
+        run: |cx, _args| perform_editor_action(cx.editor, EditorAction::CloseWindow),
+    },
+    CommandDesc {
+        name: "resize",
+        aliases: &["res"],
+        description: "set the height of the current window, or change it by +n or -n",
+        required_arguments: 1,
+        run: |cx, args| resize_window(cx, Orientation::Stacked, args[0]),
+    },
+    CommandDesc {
+        name: "vresize",
+        aliases: &["vres"],
+        description: "set the width of the current window, or change it by +n or -n",
+        required_arguments: 1,
+        run: |cx, args| resize_window(cx, Orientation::SideBySide, args[0]),
+    },
+    CommandDesc {
+        name: "wincmd",
+        aliases: &["winc"],
+        description: "focus the window in direction h, j, k or l, or the next one with w",
+        required_arguments: 1,
+        run: |cx, args| {
+            let action = match args[0] {
+                "h" => EditorAction::FocusWindow(Direction::Left),
+                "j" => EditorAction::FocusWindow(Direction::Down),
+                "k" => EditorAction::FocusWindow(Direction::Up),
+                "l" => EditorAction::FocusWindow(Direction::Right),
+                "w" => EditorAction::FocusNextWindow,
+                arg => return Err(format_err!("unknown window command '{}'", arg)),
+            };
+            perform_editor_action(cx.editor, action)
+        },
+    },
+    CommandDesc {
+        name: "tabnew",
+        aliases: &["tabe", "tabedit"],
+        description: "open a file, or a new scratch buffer, in a new tab",
+        required_arguments: 0,
+        run: |cx, args| {
+            let buffer_id = match args.first() {
+                Some(name) => open_buffer(cx.editor, name)?,
+                None => {
+                    let name = unused_buffer_name(cx.editor, "scratch");
+                    cx.editor
+                        .buffers
+                        .insert(BufferData::scratch(&name, Rope::from("\n")))
+                }
+            };
+            cx.editor.open_tab(WindowData::new(buffer_id));
+            Ok(())
+        },
+    },
+    CommandDesc {
+        name: "tabclose",
+        aliases: &["tabc"],
+        description: "close the current tab, or the one with the given number",
+        required_arguments: 0,
+        run: |cx, args| {
+            let index = match args.first() {
+                Some(number) => parse_tab_number(number)?,
+                None => cx.editor.focused_tab,
+            };
+            cx.editor.close_tab(index)
+        },
+    },
+    CommandDesc {
+        name: "tabmove",
+        aliases: &["tabm"],
+        description: "move the current tab after tab n, by +n or -n, or to the end",
+        required_arguments: 0,
+        run: |cx, args| {
+            let current = cx.editor.focused_tab;
+            let parse = |n: &str| {
+                n.parse::<usize>()
+                    .map_err(|_| format_err!("invalid tab position '{}'", n))
+            };
+            let index = match args.first() {
+                None => usize::MAX,
+                Some(arg) => {
+                    if let Some(n) = arg.strip_prefix('+') {
+                        current.saturating_add(parse(n)?)
+                    } else if let Some(n) = arg.strip_prefix('-') {
+                        current.saturating_sub(parse(n)?)
+                    } else {
+                        // After tab n, as numbered before the move.
+                        let n = parse(arg)?;
+                        if n > current {
+                            n - 1
+                        } else {
+                            n
+                        }
+                    }
+                }
+            };
+            cx.editor.move_tab(index);
+            Ok(())
+        },
+    },
+    CommandDesc {
+        name: "tab",
+        aliases: &[],
+        description: "go to the tab with the given number",
+        required_arguments: 1,
+        run: |cx, args| {
+            let index = parse_tab_number(args[0])?;
+            perform_editor_action(cx.editor, EditorAction::FocusTab(index))
+        },
+    },
+    CommandDesc {
+        name: "buffers",
+        aliases: &["ls"],
+        description: "show the list of open buffers",
+        required_arguments: 0,
+        run: |cx, _args| {
+            let list_id = cx
+                .editor
+                .buffers
+                .iter_with_handles()
+                .find(|(_, buffer)| buffer.is_buffer_list())
+                .map(|(id, _)| id);
+            let current = cx.editor.windows[cx.window].buffer;
+            let mut content = String::new();
+            for (number, (id, buffer)) in cx.editor.buffers.iter_with_handles().enumerate() {
+                if Some(id) == list_id {
+                    continue;
+                }
+                content.push_str(&format!(
+                    "{:>3} {}{} {}",
+                    number + 1,
+                    if id == current { '%' } else { ' ' },
+                    if buffer.modified { '+' } else { ' ' },
+                    buffer.name,
+                ));
+                if let Some(path) = &buffer.path {
+                    content.push_str(&format!("\t{}", path.display()));
+                }
+                content.push('\n');
+            }
+            let list_id = match list_id {
+                Some(list_id) => {
+                    cx.editor.buffers[list_id].content = Rope::from(content);
+                    cx.editor.clamp_selections(list_id);
+                    list_id
+                }
+                None => cx
+                    .editor
+                    .buffers
+                    .insert(BufferData::scratch(BUFFER_LIST_NAME, Rope::from(content))),
+            };
+            cx.editor.windows[cx.window].set_buffer(list_id);
+            Ok(())
+        },
+    },
+    CommandDesc {
+        name: "buffer",
+        aliases: &["b"],
+        description: "show the buffer with the given name or number",
+        required_arguments: 1,
+        run: |cx, args| {
+            let buffer_id = cx
+                .editor
+                .find_buffer(args[0])
+                .ok_or_else(|| format_err!("no buffer '{}'", args[0]))?;
+            cx.editor.windows[cx.window].set_buffer(buffer_id);
+            Ok(())
+        },
+    },
+    CommandDesc {
+        name: "bnext",
+        aliases: &["bn"],
+        description: "show the next buffer",
+        required_arguments: 0,
+        run: |cx, _args| cycle_buffer(cx, 1),
+    },
+    CommandDesc {
+        name: "bprev",
+        aliases: &["bp"],
+        description: "show the previous buffer",
+        required_arguments: 0,
+        run: |cx, _args| cycle_buffer(cx, -1),
+    },
+    CommandDesc {
+        name: "bdelete",
+        aliases: &["bd"],
+        description: "close a buffer, the current one by default",
+        required_arguments: 0,
+        run: |cx, args| {
+            let buffer_id = match args.first() {
+                Some(name) => cx
+                    .editor
+                    .find_buffer(name)
+                    .ok_or_else(|| format_err!("no buffer '{}'", name))?,
+                None => cx.editor.windows[cx.window].buffer,
+            };
+            let buffer = &cx.editor.buffers[buffer_id];
+            if buffer.modified && !cx.force {
+                return Err(format_err!(
+                    "buffer '{}' has unsaved changes (add ! to override)",
+                    buffer.name
+                ));
+            }
+            cx.editor.remove_buffer(buffer_id);
             Ok(())
         },
     },
 ];
+
+/// Applies a `:set` argument, trying the options of the given buffer before
+/// the editor-wide ones.
+fn set_option(editor: &mut EditorData, buffer_id: BufferId, assignment: &str) -> Result<()> {
+    let buffer = &mut editor.buffers[buffer_id];
+    match options::parse_assignment(assignment) {
+        ("fileformat" | "ff" | "fileencoding" | "fenc", Some(_)) | ("bomb" | "nobomb", None)
+            if buffer.large.is_some() =>
+        {
+            Err(format_err!("cannot convert a large file"))
+        }
+        ("fileformat" | "ff", Some(value)) => {
+            let line_ending = LineEnding::from_name(value)?;
+            if line_ending != buffer.line_ending {
+                buffer.line_ending = line_ending;
+                buffer.modified = buffer.differs_from_saved();
+            }
+            Ok(())
+        }
+        ("fileencoding" | "fenc", Some(value)) => {
+            let encoding = TextEncoding::from_label(value)?;
+            if encoding != buffer.encoding {
+                buffer.encoding = encoding;
+                buffer.modified = buffer.differs_from_saved();
+            }
+            Ok(())
+        }
+        ("bomb", None) | ("nobomb", None) => {
+            let bom = assignment == "bomb";
+            if bom != buffer.encoding.bom {
+                buffer.encoding.bom = bom;
+                buffer.modified = buffer.differs_from_saved();
+            }
+            Ok(())
+        }
+        _ => editor.options.set(assignment),
+    }
+}
+
+fn quit(cx: Context) -> Result<()> {
+    if !cx.force {
+        if let Some(buffer) = cx.editor.buffers.iter().find(|buffer| buffer.modified) {
+            return Err(format_err!(
+                "buffer '{}' has unsaved changes (add ! to override)",
+                buffer.name
+            ));
+        }
+    }
+    perform_editor_action(cx.editor, EditorAction::Quit)
+}
+
+/// Finds the buffer for a file, opening it if it isn't open yet.
+fn open_buffer(editor: &mut EditorData, name: &str) -> Result<BufferId> {
+    let path = PathBuf::from(name).canonicalize()?;
+    Ok(match editor.find_buffer_by_path(&path) {
+        Some(buffer_id) => buffer_id,
+        None => editor
+            .buffers
+            .insert(BufferData::open(String::from(name), path, &editor.options)?),
+    })
+}
+
+/// `base`, or `base` with the first number that makes it unlike the names of
+/// the open buffers.
+fn unused_buffer_name(editor: &EditorData, base: &str) -> String {
+    (1..)
+        .map(|n| match n {
+            1 => String::from(base),
+            n => format!("{} {}", base, n),
+        })
+        .find(|name| editor.buffers.iter().all(|buffer| &buffer.name != name))
+        .expect("ran out of buffer names")
+}
+
+/// The zero-based index of a one-based tab number.
+fn parse_tab_number(number: &str) -> Result<usize> {
+    number
+        .parse::<usize>()
+        .ok()
+        .and_then(|n| n.checked_sub(1))
+        .ok_or_else(|| format_err!("invalid tab number '{}'", number))
+}
+
+/// Resizes the current window to `size` rows or columns, or by them for
+/// `+n` and `-n`.
+fn resize_window(cx: Context, orientation: Orientation, size: &str) -> Result<()> {
+    let parse = |n: &str| {
+        n.parse::<isize>()
+            .map_err(|_| format_err!("invalid size '{}'", size))
+    };
+    let delta = if let Some(n) = size.strip_prefix('+') {
+        parse(n)?
+    } else if size.starts_with('-') {
+        parse(size)?
+    } else {
+        let tab = &cx.editor.open_tabs[cx.editor.focused_tab];
+        let area = tab
+            .areas()
+            .into_iter()
+            .find(|&(id, _)| id == cx.window)
+            .map(|(_, area)| area)
+            .context("window is not in the tab")?;
+        let current = match orientation {
+            Orientation::Stacked => area.height,
+            Orientation::SideBySide => area.width,
+        };
+        parse(size)? - current as isize
+    };
+    perform_editor_action(cx.editor, EditorAction::ResizeWindow(orientation, delta))
+}
+
+fn cycle_buffer(cx: Context, offset: isize) -> Result<()> {
+    let ids: Vec<BufferId> = cx
+        .editor
+        .buffers
+        .iter_with_handles()
+        .filter(|(_, buffer)| !buffer.is_buffer_list())
+        .map(|(id, _)| id)
+        .collect();
+    if ids.is_empty() {
+        return Ok(());
+    }
+    let window = &mut cx.editor.windows[cx.window];
+    let next = match ids.iter().position(|&id| id == window.buffer) {
+        Some(current) => (current as isize + offset).rem_euclid(ids.len() as isize) as usize,
+        // From the buffer list, to the first or the last buffer.
+        None if offset > 0 => 0,
+        None => ids.len() - 1,
+    };
+    window.set_buffer(ids[next]);
+    Ok(())
+}
@@ -1,4 +1,4 @@
-use crate::BufferData;
+use crate::{display, grapheme, BufferData};
 use ropey::{Rope, RopeSlice};
 use std::{mem::swap, ops::Range};
 use thiserror::Error;
@@ -60,6 +60,17 @@ impl LineIndex {
     pub fn is_empty(self, rope: &Rope) -> bool {
         self.slice_of(rope).len_chars() == 0
     }
+
+    /// The last line that has any chars, which is the one before the empty
+    /// line after a final newline.
+    pub fn last_of(rope: &Rope) -> LineIndex {
+        let last = LineIndex::from_one_based(rope.len_lines());
+        if last.is_empty(rope) && !last.is_first() {
+            LineIndex(last.0 - 1)
+        } else {
+            last
+        }
+    }
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
@@ -73,6 +84,8 @@ impl ColumnIndex {
     }
 }
 
+/// A line and column, which is how positions are shown to the user. Buffers
+/// are edited through `CharIndex` instead.
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
 pub struct Position {
     pub line: LineIndex,
@@ -87,6 +100,14 @@ impl Position {
         }
     }
 
+    pub fn from_char(rope: &Rope, char_idx: usize) -> Self {
+        let line = rope.char_to_line(char_idx);
+        Self {
+            line: LineIndex::from_zero_based(line),
+            column: ColumnIndex::from_zero_based(char_idx - rope.line_to_char(line)),
+        }
+    }
+
     pub fn char_of(self, rope: &Rope) -> usize {
         self.line.char_of(rope) + self.column.zero_based()
     }
@@ -94,164 +115,196 @@ impl Position {
     pub fn is_valid(self, rope: &Rope) -> bool {
         self.column.one_based() <= self.line.slice_of(rope).len_chars()
     }
+}
 
-    #[allow(dead_code)]
-    pub fn is_full_line(self, rope: &Rope) -> bool {
-        self.line.slice_of(rope).len_chars() == self.column.zero_based()
+/// A zero-based char offset into a buffer, which is what selections are made
+/// of. Outside of edits it points at the start of a grapheme cluster before
+/// the end of the rope, unless the rope is empty.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Default)]
+pub struct CharIndex(pub usize);
+
+impl CharIndex {
+    /// The char at `position`, or the last one of its line if the column is
+    /// past its end.
+    pub fn from_position(rope: &Rope, position: Position) -> Self {
+        let line = position.line.min(LineIndex::last_of(rope));
+        let mut char_idx = CharIndex(line.char_of(rope) + position.column.zero_based());
+        char_idx.clamp_to_line(rope, line);
+        char_idx
+    }
+
+    pub fn position(self, rope: &Rope) -> Position {
+        Position::from_char(rope, self.0)
+    }
+
+    pub fn line(self, rope: &Rope) -> LineIndex {
+        LineIndex::from_zero_based(rope.char_to_line(self.0))
     }
 
     pub fn insert_char(self, buffer: &mut BufferData, c: char) {
-        buffer.content.insert_char(self.char_of(&buffer.content), c);
-    }
-
-    pub fn validate(&mut self, rope: &Rope) {
-        if !self.is_valid(rope) {
-            if self.line.is_empty(rope) {
-                if !self.line.is_first() {
-                    self.move_to(rope, Movement::Up(1)).unwrap();
-                    self.move_to(rope, Movement::LineEnd).unwrap();
-                } else {
-                    assert_eq!(rope.len_chars(), 0);
-                    self.line = LineIndex::from_one_based(1);
-                    self.column = ColumnIndex::from_one_based(1);
-                    panic!("{}", MovementError::SelectionEmpty);
-                }
-            } else {
-                self.move_to(rope, Movement::LineEnd).unwrap();
-            }
+        buffer.content.insert_char(self.0, c);
+    }
+
+    /// Moves back onto the last cluster of the rope if the index points past
+    /// it, and to the start of the cluster it is in.
+    pub fn clamp_to(&mut self, rope: &Rope) {
+        if self.0 >= rope.len_chars() {
+            self.0 = grapheme::prev_boundary(rope.slice(..), rope.len_chars());
+        } else {
+            self.snap_to_grapheme(rope);
         }
     }
 
-    pub fn validate_fix(&mut self, buffer: &mut BufferData) {
-        if !self.is_valid(&buffer.content) {
-            if self.line.is_empty(&buffer.content) {
-                if !self.line.is_first() {
-                    self.move_to(&buffer.content, Movement::Up(1)).unwrap();
-                    self.move_to(&buffer.content, Movement::LineEnd).unwrap();
-                } else {
-                    assert_eq!(buffer.content.len_chars(), 0);
-                    self.line = LineIndex::from_one_based(1);
-                    self.column = ColumnIndex::from_one_based(1);
-                    self.insert_char(buffer, '\n');
-                }
-            } else {
-                self.move_to(&buffer.content, Movement::LineEnd).unwrap();
+    /// Moves to the start of the grapheme cluster the index is in, if it
+    /// points inside one.
+    pub fn snap_to_grapheme(&mut self, rope: &Rope) {
+        self.0 = grapheme::floor_boundary(rope.slice(..), self.0);
+    }
+
+    /// Moves onto the last cluster of `line` if the index is past it.
+    fn clamp_to_line(&mut self, rope: &Rope, line: LineIndex) {
+        let start = line.char_of(rope);
+        let text = line.slice_of(rope);
+        if self.0 >= start + text.len_chars() {
+            self.0 = start + grapheme::prev_boundary(text, text.len_chars());
+        } else {
+            self.snap_to_grapheme(rope);
+        }
+    }
+
+    /// The screen column the index is drawn at.
+    pub fn screen_column(self, rope: &Rope, tab_width: usize) -> usize {
+        let line = self.line(rope);
+        let text = line.slice_of(rope).to_string();
+        display::screen_column(&text, self.0 - line.char_of(rope), tab_width)
+    }
+
+    /// Moves to the cluster drawn at `screen_column` on the same line, or to
+    /// the end of the line if it is shorter.
+    pub fn set_screen_column(&mut self, rope: &Rope, screen_column: usize, tab_width: usize) {
+        let line = self.line(rope);
+        let text = line.slice_of(rope).to_string();
+        let column = display::buffer_column(&text, screen_column, tab_width);
+        self.0 = line.char_of(rope) + column;
+    }
+
+    /// Like `move_to`, but moving up and down goes to `desired_column` on
+    /// the screen rather than keeping the char column, so that tabs and wide
+    /// characters line up. Other movements reset `desired_column`.
+    pub fn move_visually(
+        &mut self,
+        rope: &Rope,
+        movement: Movement,
+        tab_width: usize,
+        desired_column: &mut Option<usize>,
+    ) -> Result<(), MovementError> {
+        match movement {
+            Movement::Up(_) | Movement::Down(_) => {
+                self.clamp_to(rope);
+                let screen_column =
+                    *desired_column.get_or_insert_with(|| self.screen_column(rope, tab_width));
+                self.move_to(rope, movement)?;
+                self.set_screen_column(rope, screen_column, tab_width);
+            }
+            _ => {
+                self.move_to(rope, movement)?;
+                *desired_column = None;
             }
         }
+        Ok(())
     }
 
+    /// Applies a movement. Moving left or right by more clusters than there
+    /// are moves as far as possible before failing.
     pub fn move_to(&mut self, rope: &Rope, movement: Movement) -> Result<(), MovementError> {
+        self.clamp_to(rope);
+        let all = rope.slice(..);
         match movement {
             Movement::Left(n) => {
-                if n == 0 {
-                    return Ok(());
-                }
-                // TODO: remove the loop
-                let mut moved = false;
-                for _ in 0..n {
-                    self.validate(rope);
-                    if self.column.is_first() {
-                        if !self.line.is_first() {
-                            self.move_to(rope, Movement::Up(1))?;
-                            self.move_to(rope, Movement::LineEnd)?;
-                            moved = true;
-                        } else {
-                            return Err(MovementError::NoPrevLine);
-                        }
-                    } else {
-                        self.column.0 -= 1;
-                        moved = true;
-                    }
-                }
-                if !moved {
+                let (char_idx, moved) = grapheme::backward(all, self.0, n);
+                self.0 = char_idx;
+                if moved < n {
                     return Err(MovementError::NoPrevLine);
                 }
             }
             Movement::Right(n) => {
-                if n == 0 {
-                    return Ok(());
-                }
-                // TODO: remove the loop
-                let mut moved = false;
-                for _ in 0..n {
-                    self.validate(rope);
-                    if self.column.one_based() == self.line.slice_of(rope).len_chars() {
-                        self.move_to(rope, Movement::Down(1))?;
-                        self.move_to(rope, Movement::LineStart)?;
-                        moved = true;
-                    } else {
-                        self.column.0 += 1;
-                        moved = true;
-                    }
-                }
-                if !moved {
+                // The cursor can go as far as the last cluster, which is
+                // usually the final newline.
+                let last = grapheme::prev_boundary(all, rope.len_chars());
+                let (char_idx, moved) = grapheme::forward(all, self.0, n, last);
+                self.0 = char_idx;
+                if moved < n {
                     return Err(MovementError::NoNextLine);
                 }
             }
+            Movement::Up(n) | Movement::Down(n) if n == 0 => {}
             Movement::Up(n) => {
-                if n == 0 {
-                    return Ok(());
-                }
-                let n = n.min(self.line.zero_based());
+                let line = self.line(rope);
+                let n = n.min(line.zero_based());
                 if n == 0 {
                     return Err(MovementError::NoPrevLine);
                 }
-                self.line.0 -= n;
+                self.move_to_line(rope, LineIndex(line.0 - n));
             }
             Movement::Down(n) => {
+                let line = self.line(rope);
+                let n = n.min(LineIndex::last_of(rope).0.saturating_sub(line.0));
                 if n == 0 {
-                    return Ok(());
-                }
-                // TODO: remove the loop
-                let mut moved = false;
-                for _ in 0..n {
-                    if !self.line.is_last(rope)
-                        && LineIndex(self.line.0 + 1).slice_of(rope).len_chars() > 0
-                    {
-                        self.line.0 += 1;
-                        moved = true;
-                    } else {
-                        break;
-                    }
-                }
-                if !moved {
                     return Err(MovementError::NoNextLine);
                 }
+                self.move_to_line(rope, LineIndex(line.0 + n));
             }
             Movement::LineStart => {
-                self.column = ColumnIndex::from_one_based(1);
+                self.0 = self.line(rope).char_of(rope);
             }
             Movement::LineEnd => {
-                self.column = ColumnIndex::from_one_based(self.line.slice_of(rope).len_chars());
+                let line = self.line(rope);
+                self.0 = usize::MAX;
+                self.clamp_to_line(rope, line);
             }
             Movement::FileStart => {
-                self.line = LineIndex::from_one_based(1);
-                self.move_to(rope, Movement::LineStart)?;
+                self.0 = 0;
             }
             Movement::FileEnd => {
-                let last = LineIndex::from_one_based(rope.len_lines());
-                if !last.is_empty(rope) {
-                    self.line = last;
-                } else {
-                    self.line = LineIndex(last.0 - 1);
-                }
-                self.move_to(rope, Movement::LineStart)?;
+                self.0 = LineIndex::last_of(rope).char_of(rope);
             }
         }
         Ok(())
     }
+
+    /// Moves to the same char column on another line, or its end.
+    fn move_to_line(&mut self, rope: &Rope, line: LineIndex) {
+        let column = self.0 - self.line(rope).char_of(rope);
+        self.0 = line.char_of(rope) + column;
+        self.clamp_to_line(rope, line);
+    }
 }
 
 #[derive(Debug, Copy, Clone)]
 pub struct Selection {
-    pub start: Position,
-    pub end: Position,
+    pub start: CharIndex,
+    pub end: CharIndex,
+    /// The screen column moving up and down aims for, so that passing
+    /// through a short line doesn't lose it. `None` means the column of the
+    /// index being moved, and horizontal moves and edits reset it to that.
+    pub desired_column: Option<usize>,
 }
 
 impl Selection {
+    pub fn new(char_idx: CharIndex) -> Self {
+        Self {
+            start: char_idx,
+            end: char_idx,
+            desired_column: None,
+        }
+    }
+
+    /// The chars covered by the selection, which includes the whole grapheme
+    /// cluster its last index is on.
     pub fn range_of(mut self, rope: &Rope) -> Range<usize> {
         self.order();
-        self.start.char_of(rope)..self.end.char_of(rope) + 1
+        let end = grapheme::next_boundary(rope.slice(..), self.end.0.min(rope.len_chars()));
+        self.start.0.min(end)..end
     }
 
     pub fn slice_of(self, rope: &Rope) -> RopeSlice<'_> {
@@ -264,13 +317,12 @@ impl Selection {
         }
     }
 
-    #[allow(dead_code)]
     pub fn ordered(mut self) -> Self {
         self.order();
         self
     }
 
-    pub fn contains(mut self, other: Position) -> bool {
+    pub fn contains(mut self, other: CharIndex) -> bool {
         self.order();
         other >= self.start && other <= self.end
     }
@@ -291,30 +343,52 @@ impl Selection {
         self.start <= ordered.end
     }
 
-    pub fn valid(mut self, rope: &Rope) -> Self {
-        self.start.validate(rope);
-        self.end.validate(rope);
-        self
+    pub fn clamp_to(&mut self, rope: &Rope) {
+        self.start.clamp_to(rope);
+        self.end.clamp_to(rope);
     }
 
-    pub fn validate(&mut self, rope: &Rope) {
-        self.start.validate(rope);
-        self.end.validate(rope);
+    pub fn snap_to_graphemes(&mut self, rope: &Rope) {
+        self.start.snap_to_grapheme(rope);
+        self.end.snap_to_grapheme(rope);
     }
 
-    pub fn validate_fix(&mut self, buffer: &mut BufferData) {
-        self.start.validate_fix(buffer);
-        self.end.validate_fix(buffer);
+    /// Keeps the selection on the same text after `len` chars were inserted
+    /// at `char_idx` by another selection.
+    pub fn shift_for_insert(&mut self, char_idx: usize, len: usize) {
+        for index in [&mut self.start, &mut self.end] {
+            if index.0 >= char_idx {
+                index.0 += len;
+            }
+        }
     }
 
-    pub fn remove_from(&mut self, buffer: &mut BufferData) {
-        self.validate(&buffer.content);
+    /// Keeps the selection on the same text after `range` was removed by
+    /// another selection.
+    pub fn shift_for_remove(&mut self, range: Range<usize>) {
+        for index in [&mut self.start, &mut self.end] {
+            if index.0 >= range.end {
+                index.0 -= range.len();
+            } else if index.0 > range.start {
+                index.0 = range.start;
+            }
+        }
+    }
+
+    /// Removes the selected text, leaving an empty selection where it was.
+    /// Returns the range that was removed.
+    pub fn remove_from(&mut self, buffer: &mut BufferData) -> Range<usize> {
+        self.clamp_to(&buffer.content);
         self.order();
         let range = self.range_of(&buffer.content);
-        buffer.content.remove(range);
+        buffer.content.remove(range.clone());
         self.end = self.start;
-        self.validate_fix(buffer);
+        if buffer.content.len_chars() == 0 {
+            buffer.content.insert_char(0, '\n');
+        }
+        self.clamp_to(&buffer.content);
         // TODO: the file must be terminated by a final newline
+        range
     }
 
     pub fn move_to(
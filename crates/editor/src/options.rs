@@ -0,0 +1,131 @@
+use crate::status::StatusLine;
+use anyhow::{format_err, Result};
+
+/// Wider tabs would only be a way to run out of memory drawing them.
+const MAX_TAB_WIDTH: u64 = 64;
+
+/// Editor-wide settings changed with `:set`.
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// Keep the previous contents of a file as `name~` when writing it.
+    pub backup: bool,
+    /// Files of at least this many bytes are opened page by page instead of
+    /// being read whole. Set in MiB with `largefile`.
+    pub large_file_threshold: u64,
+    /// Tabs are drawn up to the next multiple of this many columns.
+    pub tab_width: usize,
+    /// Break lines that don't fit the window onto more rows, instead of
+    /// scrolling sideways.
+    pub wrap: bool,
+    /// Rows kept visible above and below the cursor when scrolling.
+    pub scrolloff: usize,
+    /// Which line numbers the gutter shows.
+    pub line_numbers: LineNumbers,
+    /// Columns reserved at the start of the gutter for signs such as
+    /// diagnostics, version control changes and marks.
+    pub sign_columns: usize,
+    /// What the status line shows after the mode.
+    pub status_line: StatusLine,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum LineNumbers {
+    Off,
+    Absolute,
+    /// The distance from the cursor's line, which is what counts take.
+    Relative,
+    /// Relative numbers, except on the cursor's line.
+    Hybrid,
+}
+
+impl LineNumbers {
+    pub fn from_name(name: &str) -> Result<LineNumbers> {
+        match name {
+            "off" => Ok(LineNumbers::Off),
+            "absolute" => Ok(LineNumbers::Absolute),
+            "relative" => Ok(LineNumbers::Relative),
+            "hybrid" => Ok(LineNumbers::Hybrid),
+            _ => Err(format_err!("unknown line number mode '{}'", name)),
+        }
+    }
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            backup: false,
+            large_file_threshold: 64 << 20,
+            tab_width: 4,
+            wrap: true,
+            scrolloff: 3,
+            line_numbers: LineNumbers::Off,
+            sign_columns: 0,
+            status_line: StatusLine::default(),
+        }
+    }
+}
+
+impl Options {
+    /// Applies a single `:set` argument: `name=value`, `name` or `noname`.
+    pub fn set(&mut self, assignment: &str) -> Result<()> {
+        let (name, value) = parse_assignment(assignment);
+        match name {
+            "backup" | "bk" => self.backup = parse_bool(name, value)?,
+            "nobackup" | "nobk" if value.is_none() => self.backup = false,
+            "largefile" => self.large_file_threshold = parse_number(name, value)? << 20,
+            "tabwidth" | "ts" => match parse_number(name, value)? {
+                0 => return Err(format_err!("option '{}' must be at least 1", name)),
+                width if width > MAX_TAB_WIDTH => {
+                    return Err(format_err!(
+                        "option '{}' must be at most {}",
+                        name,
+                        MAX_TAB_WIDTH
+                    ))
+                }
+                width => self.tab_width = width as usize,
+            },
+            "wrap" => self.wrap = parse_bool(name, value)?,
+            "nowrap" if value.is_none() => self.wrap = false,
+            "scrolloff" | "so" => self.scrolloff = parse_number(name, value)? as usize,
+            "number" | "nu" => match value {
+                None => self.line_numbers = LineNumbers::Absolute,
+                Some(value) => self.line_numbers = LineNumbers::from_name(value)?,
+            },
+            "nonumber" | "nonu" if value.is_none() => self.line_numbers = LineNumbers::Off,
+            "signcolumns" | "scl" => self.sign_columns = parse_number(name, value)? as usize,
+            "statusline" | "stl" => match value {
+                None => self.status_line = StatusLine::default(),
+                Some(value) => self.status_line = StatusLine::parse(value)?,
+            },
+            _ => return Err(format_err!("unknown option '{}'", name)),
+        }
+        Ok(())
+    }
+}
+
+/// Splits a `:set` argument into the option name and its value, if any.
+pub fn parse_assignment(assignment: &str) -> (&str, Option<&str>) {
+    match assignment.split_once('=') {
+        Some((name, value)) => (name, Some(value)),
+        None => (assignment, None),
+    }
+}
+
+pub fn parse_number(name: &str, value: Option<&str>) -> Result<u64> {
+    let value = value.ok_or_else(|| format_err!("option '{}' needs a value", name))?;
+    value
+        .parse()
+        .map_err(|_| format_err!("invalid value '{}' for option '{}'", value, name))
+}
+
+pub fn parse_bool(name: &str, value: Option<&str>) -> Result<bool> {
+    match value {
+        None | Some("true") | Some("on") => Ok(true),
+        Some("false") | Some("off") => Ok(false),
+        Some(value) => Err(format_err!(
+            "invalid value '{}' for option '{}'",
+            value,
+            name
+        )),
+    }
+}
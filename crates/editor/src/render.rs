@@ -0,0 +1,749 @@
+//! A picture of the editor that frontends paint as it is: the tab line, the
+//! windows as grids of styled cells with their cursors, the status line and
+//! popups over them. Layout, scrolling and styling are all decided here, so
+//! a frontend only has to map cells and styles to its own output.
+//!
+//! Coordinates are zero-based cells from the top left of the screen.
+
+use crate::{
+    display, hex,
+    large::LargeFile,
+    layout::{Border, Orientation},
+    location::LineIndex,
+    options::{LineNumbers, Options},
+    EditorData, Importance, Mode, WindowId,
+};
+use std::{iter::Peekable, ops::Range};
+use unicode_segmentation::UnicodeSegmentation;
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub enum Color {
+    #[default]
+    Default,
+    Black,
+    Red,
+    Yellow,
+    LightYellow,
+    White,
+    Gray,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub struct Style {
+    pub fg: Color,
+    pub bg: Color,
+    pub bold: bool,
+    /// Swap the foreground and background colors, which is how selections
+    /// are shown.
+    pub inverse: bool,
+}
+
+impl Style {
+    pub fn fg(color: Color) -> Style {
+        Style {
+            fg: color,
+            ..Style::default()
+        }
+    }
+
+    pub fn inverse() -> Style {
+        Style {
+            inverse: true,
+            ..Style::default()
+        }
+    }
+
+    pub fn bold() -> Style {
+        Style {
+            bold: true,
+            ..Style::default()
+        }
+    }
+}
+
+/// One cell of a grid. A cluster wider than one cell is in its first cell,
+/// and the cells it covers after that are empty.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Cell {
+    pub text: String,
+    pub style: Style,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Cell {
+            text: String::from(" "),
+            style: Style::default(),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Point {
+    pub x: usize,
+    pub y: usize,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Rect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+/// A rectangle of cells, blank when created.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Grid {
+    pub width: usize,
+    pub height: usize,
+    cells: Vec<Cell>,
+}
+
+impl Grid {
+    pub fn new(width: usize, height: usize) -> Grid {
+        Grid {
+            width,
+            height,
+            cells: vec![Cell::default(); width * height],
+        }
+    }
+
+    pub fn cell(&self, x: usize, y: usize) -> &Cell {
+        &self.cells[y * self.width + x]
+    }
+
+    pub fn cell_mut(&mut self, x: usize, y: usize) -> &mut Cell {
+        &mut self.cells[y * self.width + x]
+    }
+
+    pub fn row(&self, y: usize) -> &[Cell] {
+        &self.cells[y * self.width..(y + 1) * self.width]
+    }
+
+    /// The text of a row, with the cells covered by wide clusters left out.
+    pub fn row_text(&self, y: usize) -> String {
+        self.row(y).iter().map(|cell| cell.text.as_str()).collect()
+    }
+
+    /// Puts a cluster `width` cells wide at `x`, if it fits on the row.
+    /// Wide clusters it partly covers are blanked. Returns the column after
+    /// it.
+    pub fn put(&mut self, x: usize, y: usize, cluster: &str, width: usize, style: Style) -> usize {
+        if y >= self.height || x + width > self.width {
+            return x + width;
+        }
+        let mut start = x;
+        while start > 0 && self.cell(start, y).text.is_empty() {
+            start -= 1;
+            self.cell_mut(start, y).text = String::from(" ");
+        }
+        let mut end = x + width;
+        while end < self.width && self.cell(end, y).text.is_empty() {
+            self.cell_mut(end, y).text = String::from(" ");
+            end += 1;
+        }
+        *self.cell_mut(x, y) = Cell {
+            text: String::from(cluster),
+            style,
+        };
+        for x in x + 1..x + width {
+            *self.cell_mut(x, y) = Cell {
+                text: String::new(),
+                style,
+            };
+        }
+        x + width
+    }
+
+    /// Writes text from `x`, cutting it off at the end of the row. Returns
+    /// the column after it.
+    pub fn print(&mut self, mut x: usize, y: usize, text: &str, style: Style) -> usize {
+        for cluster in text.graphemes(true) {
+            x = self.put(x, y, cluster, display::cluster_width(cluster).max(1), style);
+        }
+        x
+    }
+
+    /// Sets the style of every cell of a row from `x` on.
+    pub fn fill_style(&mut self, x: usize, y: usize, style: Style) {
+        for x in x..self.width {
+            self.cell_mut(x, y).style = style;
+        }
+    }
+
+    /// Copies `other` onto this grid with its top left corner at `at`,
+    /// cutting off what doesn't fit.
+    pub fn blit(&mut self, other: &Grid, at: Point) {
+        for y in 0..other.height.min(self.height.saturating_sub(at.y)) {
+            for x in 0..other.width.min(self.width.saturating_sub(at.x)) {
+                *self.cell_mut(at.x + x, at.y + y) = other.cell(x, y).clone();
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Tab {
+    pub label: String,
+    pub modified: bool,
+    pub focused: bool,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct WindowView {
+    pub window: WindowId,
+    pub area: Rect,
+    pub grid: Grid,
+    /// Where the ends of the selections are drawn, relative to `area`.
+    pub cursors: Vec<Point>,
+    pub primary_cursor: Option<Point>,
+    pub focused: bool,
+}
+
+/// Something drawn over the windows, such as a message too long for the
+/// status line.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Popup {
+    pub area: Rect,
+    pub grid: Grid,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Frame {
+    pub width: usize,
+    pub height: usize,
+    pub tabs: Vec<Tab>,
+    pub tab_line: Grid,
+    pub windows: Vec<WindowView>,
+    pub borders: Vec<Border>,
+    pub status_line: Grid,
+    pub popups: Vec<Popup>,
+}
+
+impl Frame {
+    /// The whole screen as one grid, with popups on top.
+    pub fn compose(&self) -> Grid {
+        let mut grid = Grid::new(self.width, self.height);
+        grid.blit(&self.tab_line, Point { x: 0, y: 0 });
+        for window in &self.windows {
+            grid.blit(
+                &window.grid,
+                Point {
+                    x: window.area.x,
+                    y: window.area.y,
+                },
+            );
+        }
+        let style = Style::fg(Color::Gray);
+        for border in &self.borders {
+            let line = match border.orientation {
+                Orientation::Stacked => "\u{2500}",
+                Orientation::SideBySide => "\u{2502}",
+            };
+            let area = border.area;
+            for y in area.y..(area.y + area.height).min(grid.height) {
+                for x in area.x..(area.x + area.width).min(grid.width) {
+                    grid.put(x, y, line, 1, style);
+                }
+            }
+        }
+        grid.blit(
+            &self.status_line,
+            Point {
+                x: 0,
+                y: self.height.saturating_sub(1),
+            },
+        );
+        for popup in &self.popups {
+            grid.blit(
+                &popup.grid,
+                Point {
+                    x: popup.area.x,
+                    y: popup.area.y,
+                },
+            );
+        }
+        grid
+    }
+}
+
+/// Lays out the editor on a screen of `width` by `height` cells. Windows
+/// are scrolled to their cursors on the way, and a pending message is shown
+/// and then dropped.
+pub fn render(editor: &mut EditorData, width: usize, height: usize) -> Frame {
+    let tabs = tabs(editor);
+    let tab_line = tab_line(editor, &tabs, width);
+
+    let area = Rect {
+        x: 0,
+        y: 1,
+        width,
+        height: height.saturating_sub(2),
+    };
+    let tab = &mut editor.open_tabs[editor.focused_tab];
+    tab.area = Some(area);
+    let mut areas = Vec::new();
+    let mut borders = Vec::new();
+    tab.layout.arrange(area, &mut areas, &mut borders);
+    let mut windows = Vec::new();
+    for (window_id, area) in areas {
+        if window_id == editor.focused_window() {
+            editor.last_screen_height = Some(area.height.saturating_sub(1) as u16);
+        }
+        windows.push(render_window(editor, window_id, area));
+    }
+
+    let (status_line, popups) = status_line(editor, width, height);
+    Frame {
+        width,
+        height,
+        tabs,
+        tab_line,
+        windows,
+        borders,
+        status_line,
+        popups,
+    }
+}
+
+fn tabs(editor: &EditorData) -> Vec<Tab> {
+    editor
+        .open_tabs
+        .iter()
+        .enumerate()
+        .map(|(i, tab)| {
+            let buffer = &editor.buffers[editor.windows[tab.focused].buffer];
+            Tab {
+                label: buffer.name.clone(),
+                modified: buffer.modified,
+                focused: i == editor.focused_tab,
+            }
+        })
+        .collect()
+}
+
+/// Draws the tab labels in order, scrolled so that the focused one shows,
+/// with `<` and `>` where tabs are cut off. Where each label went is kept
+/// in the tab for mouse clicks.
+fn tab_line(editor: &mut EditorData, tabs: &[Tab], width: usize) -> Grid {
+    let mut grid = Grid::new(width, 1);
+    let labels: Vec<String> = tabs
+        .iter()
+        .map(|tab| {
+            if tab.modified {
+                format!("{} [+]", tab.label)
+            } else {
+                tab.label.clone()
+            }
+        })
+        .collect();
+    // Each label is followed by a space.
+    let widths: Vec<usize> = labels
+        .iter()
+        .map(|label| {
+            display::glyphs(label, 1)
+                .map(|glyph| glyph.width)
+                .sum::<usize>()
+                + 1
+        })
+        .collect();
+    let focused = editor.focused_tab;
+    let mut first = 0;
+    let end = loop {
+        let mut x = if first > 0 { 2 } else { 0 };
+        let mut end = first;
+        while end < tabs.len() && x + widths[end] <= width {
+            x += widths[end];
+            end += 1;
+        }
+        // Leave room for the `>`.
+        while end < tabs.len() && end > first && x + 1 > width {
+            end -= 1;
+            x -= widths[end];
+        }
+        if focused < end {
+            break end;
+        } else if first == focused {
+            // Too long to fit, so it's cut off at the edge.
+            break first + 1;
+        }
+        first += 1;
+    };
+
+    for tab in editor.open_tabs.iter_mut() {
+        tab.label = None;
+    }
+    let mut x = 0;
+    if first > 0 {
+        x = grid.print(x, 0, "<", Style::default());
+        x = grid.print(x, 0, " ", Style::default());
+    }
+    for i in first..end {
+        let style = if tabs[i].focused {
+            Style::bold()
+        } else {
+            Style::default()
+        };
+        let start = x;
+        x = grid.print(x, 0, &labels[i], style);
+        editor.open_tabs[i].label = Some(start.min(width)..x.min(width));
+        x = grid.print(x, 0, " ", Style::default());
+    }
+    if end < tabs.len() && width > 0 {
+        grid.put(width - 1, 0, ">", 1, Style::default());
+    }
+    grid
+}
+
+fn render_window(editor: &mut EditorData, window_id: WindowId, area: Rect) -> WindowView {
+    let mut view = WindowView {
+        window: window_id,
+        area,
+        grid: Grid::new(area.width, area.height),
+        cursors: Vec::new(),
+        primary_cursor: None,
+        focused: window_id == editor.focused_window(),
+    };
+    if area.width == 0 || area.height == 0 {
+        return view;
+    }
+    if editor.buffers[editor.windows[window_id].buffer]
+        .binary
+        .is_some()
+    {
+        render_hex_window(editor, &mut view);
+    } else {
+        render_text_window(editor, &mut view);
+    }
+    view
+}
+
+fn render_text_window(editor: &mut EditorData, view: &mut WindowView) {
+    let window = &mut editor.windows[view.window];
+    let buffer = &editor.buffers[window.buffer];
+    let options = &editor.options;
+    let rope = &buffer.content;
+    let grid = &mut view.grid;
+    let line_count = LineIndex::last_of(rope).one_based();
+    // The lines of a large file are numbered from the start of the file.
+    let first_line = buffer
+        .large
+        .as_ref()
+        .map_or(0, LargeFile::lines_before_current_page);
+    let gutter_width = display::gutter_width(
+        options.line_numbers,
+        first_line + line_count,
+        options.sign_columns,
+    )
+    .min(grid.width - 1);
+    let width = grid.width - gutter_width;
+    let height = grid.height;
+    window.scroll_to_cursor(buffer, options, width, height);
+
+    let primary = window.selections[window.primary_selection].end;
+    let cursor_line = primary.line(rope).zero_based();
+    let first_char = window.top.char_of(rope);
+    let last_line = (window.top.zero_based() + height + 1).min(rope.len_lines());
+    let last_char = rope.line_to_char(last_line);
+    let ranges = window.selection_ranges(rope, first_char..last_char);
+    let mut ranges = ranges.iter().peekable();
+    let mut cursors: Vec<usize> = window
+        .selections
+        .iter()
+        .map(|selection| selection.end.0)
+        .filter(|char_idx| (first_char..last_char).contains(char_idx))
+        .collect();
+    cursors.sort_unstable();
+
+    let mut y = 0;
+    let mut skip_rows = if options.wrap { window.top_row } else { 0 };
+    'outer: for line in window.top.zero_based()..line_count {
+        let text = LineIndex::from_zero_based(line).slice_of(rope).to_string();
+        let glyphs: Vec<_> = display::glyphs(&text, options.tab_width).collect();
+        let line_start = rope.line_to_char(line);
+        let number = display::line_number(
+            options.line_numbers,
+            first_line + line,
+            first_line + cursor_line,
+        );
+        let is_cursor_line = line == cursor_line;
+        let rows = if options.wrap {
+            display::wrap(&glyphs, width)
+        } else {
+            vec![display::Row {
+                glyphs: 0..glyphs.len(),
+                prefix: 0,
+            }]
+        };
+        for (i, row) in rows.into_iter().enumerate().skip(skip_rows) {
+            if y >= height {
+                break 'outer;
+            }
+            if i == 0 {
+                render_gutter(grid, y, gutter_width, options, number, is_cursor_line);
+            }
+            let mut x = gutter_width;
+            if i > 0 {
+                let marker = display::CONTINUATION_MARKER;
+                x += row.prefix.saturating_sub(marker.chars().count());
+                x = grid.print(x, y, marker, Style::fg(Color::Gray));
+            }
+            for glyph in &glyphs[row.glyphs] {
+                let (left, end) = (window.left, glyph.screen_column + glyph.width);
+                if options.wrap {
+                    // Rows are already cut to fit.
+                } else if end > left + width {
+                    break;
+                } else if glyph.screen_column < left {
+                    // Wide glyphs cut by the left edge leave blank cells.
+                    x += end.saturating_sub(left);
+                    continue;
+                }
+                let char_idx = line_start + glyph.column;
+                let style = if in_ranges(&mut ranges, char_idx) {
+                    Style::inverse()
+                } else {
+                    Style::default()
+                };
+                if cursors.binary_search(&char_idx).is_ok() {
+                    let point = Point { x, y };
+                    view.cursors.push(point);
+                    if char_idx == primary.0 {
+                        view.primary_cursor = Some(point);
+                    }
+                }
+                x = put_glyph(grid, x, y, glyph, style);
+            }
+            y += 1;
+        }
+        skip_rows = 0;
+    }
+}
+
+/// Whether `char_idx` is in one of the sorted `ranges`, which are skipped
+/// past as the text goes forward.
+fn in_ranges<'a>(
+    ranges: &mut Peekable<impl Iterator<Item = &'a Range<usize>>>,
+    char_idx: usize,
+) -> bool {
+    while ranges.next_if(|range| range.end <= char_idx).is_some() {}
+    ranges.peek().is_some_and(|range| range.contains(&char_idx))
+}
+
+/// Puts a glyph at `x`: a single cluster as one wide cell, and escapes and
+/// tabs one cell per char.
+fn put_glyph(grid: &mut Grid, x: usize, y: usize, glyph: &display::Glyph, style: Style) -> usize {
+    let mut clusters = glyph.text.graphemes(true);
+    match (clusters.next(), clusters.next()) {
+        (Some(cluster), None) => grid.put(x, y, cluster, glyph.width, style),
+        _ => grid.print(x, y, &glyph.text, style),
+    }
+}
+
+/// Draws the sign columns, which are blank for now, and the line number
+/// right-aligned before the space that separates it from the text.
+fn render_gutter(
+    grid: &mut Grid,
+    y: usize,
+    gutter_width: usize,
+    options: &Options,
+    number: Option<usize>,
+    is_cursor_line: bool,
+) {
+    let number_width = gutter_width.saturating_sub(options.sign_columns + 1);
+    if options.line_numbers == LineNumbers::Off || number_width == 0 {
+        return;
+    }
+    let number = number.map(|n| n.to_string()).unwrap_or_default();
+    let style = if is_cursor_line {
+        Style::bold()
+    } else {
+        Style::fg(Color::Gray)
+    };
+    let x = gutter_width - 1 - number.len().min(number_width);
+    grid.print(x, y, &number, style);
+}
+
+fn render_hex_window(editor: &mut EditorData, view: &mut WindowView) {
+    let window = &mut editor.windows[view.window];
+    let cursor = window.hex_cursor;
+    window.scroll_to_hex_cursor(view.grid.height);
+    let bytes = editor.buffers[window.buffer]
+        .binary
+        .as_deref()
+        .unwrap_or_default();
+    for y in 0..view.grid.height {
+        let row = window.top.zero_based() + y;
+        if row >= hex::row_count(bytes.len()) {
+            break;
+        }
+        let column = cursor.offset % hex::BYTES_PER_ROW;
+        let hex_column = hex::hex_column(column) + usize::from(cursor.low_nibble);
+        for (x, c) in hex::format_row(bytes, row).chars().enumerate() {
+            let highlighted =
+                row == cursor.row() && (x == hex_column || x == hex::ascii_column(column));
+            let style = if highlighted {
+                Style::inverse()
+            } else {
+                Style::default()
+            };
+            view.grid.put(x, y, c.encode_utf8(&mut [0; 4]), 1, style);
+        }
+        if row == cursor.row() {
+            let point = Point { x: hex_column, y };
+            view.cursors.push(point);
+            view.primary_cursor = Some(point);
+        }
+    }
+}
+
+/// The status line, and a popup above it for messages that don't fit on
+/// one line.
+fn status_line(editor: &mut EditorData, width: usize, height: usize) -> (Grid, Vec<Popup>) {
+    let mut grid = Grid::new(width, 1);
+    let mut popups = Vec::new();
+    if let Some((importance, message)) = editor.pending_message.take() {
+        let background = match importance {
+            Importance::Error => Color::Red,
+            Importance::Warning => Color::Yellow,
+        };
+        let style = Style {
+            fg: Color::White,
+            bg: background,
+            ..Style::default()
+        };
+        let lines: Vec<&str> = message.lines().collect();
+        let message_width: usize = display::glyphs(&message, 1).map(|glyph| glyph.width).sum();
+        let fits = lines.len() <= 1 && message_width + 2 <= width;
+        if fits {
+            let x = grid.print(0, 0, " ", style);
+            let x = grid.print(x, 0, &message, style);
+            grid.print(x, 0, " ", style);
+        } else {
+            let rows = lines.len().min(height.saturating_sub(2)).max(1);
+            let mut popup = Grid::new(width, rows);
+            for (y, line) in lines.iter().take(rows).enumerate() {
+                popup.fill_style(0, y, style);
+                popup.print(1, y, line, style);
+            }
+            popups.push(Popup {
+                area: Rect {
+                    x: 0,
+                    y: height.saturating_sub(1 + rows),
+                    width,
+                    height: rows,
+                },
+                grid: popup,
+            });
+            grid.fill_style(0, 0, style);
+            grid.print(1, 0, lines.first().copied().unwrap_or_default(), style);
+        }
+        return (grid, popups);
+    }
+    let window = &editor.windows[editor.focused_window()];
+    let buffer = &editor.buffers[window.buffer];
+    let mode = window.mode;
+    let color = match mode {
+        Mode::Insert => Color::LightYellow,
+        _ => Color::White,
+    };
+    let x = grid.print(
+        0,
+        0,
+        &format!(" {:?} ", mode),
+        Style {
+            fg: color,
+            inverse: true,
+            ..Style::default()
+        },
+    );
+    if let Mode::Command = mode {
+        let x = grid.print(x, 0, &format!(" :{}", window.command), Style::default());
+        grid.print(x, 0, " ", Style::inverse());
+        return (grid, popups);
+    }
+    let (left, right) = editor.options.status_line.expand(window, buffer);
+    let x = grid.print(x, 0, &left, Style::default());
+    // When the two don't fit together, the right side gives way from its
+    // start, keeping a space after the left side.
+    let room = width.saturating_sub(x + 1);
+    let glyphs: Vec<_> = display::glyphs(&right, 1).collect();
+    let mut right_width: usize = glyphs.iter().map(|glyph| glyph.width).sum();
+    let mut shown = glyphs.as_slice();
+    while right_width > room {
+        right_width -= shown[0].width;
+        shown = &shown[1..];
+    }
+    let right: String = shown.iter().map(|glyph| glyph.text.as_ref()).collect();
+    grid.print(width - right_width, 0, &right, Style::default());
+    (grid, popups)
+}
+
+/// A run of cells on row `y` that changed between two frames.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Damage {
+    pub y: usize,
+    pub columns: Range<usize>,
+}
+
+/// Unchanged cells between two changed runs are redrawn rather than moved
+/// over when there are at most this many, which is about what moving the
+/// cursor costs on a terminal.
+const MAX_GAP: usize = 4;
+
+/// The runs of cells to redraw to turn `old` into `new`, which is all of
+/// `new` if there is no `old` of the same size. Runs always cover whole
+/// clusters in both grids, so that wide ones are never drawn by halves.
+pub fn damage(old: Option<&Grid>, new: &Grid) -> Vec<Damage> {
+    let old = match old {
+        Some(old) if old.width == new.width && old.height == new.height => old,
+        _ => {
+            return (0..new.height)
+                .map(|y| Damage {
+                    y,
+                    columns: 0..new.width,
+                })
+                .collect()
+        }
+    };
+    let is_continuation =
+        |x: usize, y: usize| old.cell(x, y).text.is_empty() || new.cell(x, y).text.is_empty();
+    let mut runs = Vec::new();
+    for y in 0..new.height {
+        let changed = |x: usize| old.cell(x, y) != new.cell(x, y);
+        let mut x = 0;
+        while x < new.width {
+            if !changed(x) {
+                x += 1;
+                continue;
+            }
+            let mut start = x;
+            while start > 0 && is_continuation(start, y) {
+                start -= 1;
+            }
+            let mut end = x + 1;
+            loop {
+                while end < new.width && is_continuation(end, y) {
+                    end += 1;
+                }
+                match (end..new.width).find(|&x| changed(x)) {
+                    Some(next) if next - end <= MAX_GAP => end = next + 1,
+                    _ => break,
+                }
+            }
+            // A run reaching back into the previous one joins it.
+            match runs.last_mut() {
+                Some(Damage { y: last_y, columns }) if *last_y == y && start <= columns.end => {
+                    columns.end = end;
+                }
+                _ => runs.push(Damage {
+                    y,
+                    columns: start..end,
+                }),
+            }
+            x = end;
+        }
+    }
+    runs
+}
@@ -0,0 +1,275 @@
+//! The text of the status line after the mode, laid out from the
+//! `statusline` template. Fields go in braces, like `{name}`, and `{=}`
+//! right-aligns what follows it; that part is cut from its start when it
+//! doesn't fit beside the rest. `{{` and `}}` stand for the braces
+//! themselves.
+//!
+//! Some fields are empty most of the time, like `{modified}`; an empty field
+//! drops one space after it, so `{name} {modified} {line}` doesn't leave two
+//! spaces for an unmodified buffer.
+
+use crate::{hex::BYTES_PER_ROW, large::LargeFile, location::LineIndex, BufferData, WindowData};
+use anyhow::{bail, format_err, Result};
+use std::{path::Path, sync::atomic::Ordering};
+
+pub const DEFAULT_TEMPLATE: &str =
+    " {name} {modified} [{fileinfo}]{=} {filetype} {selections} {line}:{column} {percent} ";
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Field {
+    /// The buffer's name.
+    Name,
+    /// The full path of the buffer's file, or nothing for a scratch buffer.
+    Path,
+    /// `[+]` when the buffer has unsaved changes.
+    Modified,
+    /// The one-based line of the primary selection's cursor.
+    Line,
+    /// The one-based column, in chars, of the primary selection's cursor.
+    Column,
+    /// How many selections there are, like `3 sel`, when there is more than
+    /// one.
+    Selections,
+    /// How far through the file the cursor's line is, like `42%`.
+    Percent,
+    Encoding,
+    /// `unix`, `dos` or `mac`.
+    LineEnding,
+    /// The encoding and line ending, or the page of a large file.
+    FileInfo,
+    /// The language, guessed from the file name.
+    FileType,
+}
+
+impl Field {
+    fn from_name(name: &str) -> Result<Field> {
+        Ok(match name {
+            "name" => Field::Name,
+            "path" => Field::Path,
+            "modified" => Field::Modified,
+            "line" => Field::Line,
+            "column" => Field::Column,
+            "selections" => Field::Selections,
+            "percent" => Field::Percent,
+            "encoding" => Field::Encoding,
+            "lineending" => Field::LineEnding,
+            "fileinfo" => Field::FileInfo,
+            "filetype" => Field::FileType,
+            _ => bail!("unknown status line field '{{{}}}'", name),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum Part {
+    Text(String),
+    Field(Field),
+    /// `{=}`: the rest is aligned to the right edge.
+    Align,
+}
+
+/// A parsed `statusline` template.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct StatusLine {
+    parts: Vec<Part>,
+}
+
+impl Default for StatusLine {
+    fn default() -> Self {
+        StatusLine::parse(DEFAULT_TEMPLATE).unwrap()
+    }
+}
+
+impl StatusLine {
+    pub fn parse(template: &str) -> Result<StatusLine> {
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut chars = template.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.as_str().starts_with('{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.as_str().starts_with('}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let rest = chars.as_str();
+                    let end = rest
+                        .find('}')
+                        .ok_or_else(|| format_err!("unclosed '{{' in status line"))?;
+                    if !text.is_empty() {
+                        parts.push(Part::Text(std::mem::take(&mut text)));
+                    }
+                    parts.push(match &rest[..end] {
+                        "=" if parts.contains(&Part::Align) => {
+                            bail!("'{{=}}' can only be used once in the status line")
+                        }
+                        "=" => Part::Align,
+                        name => Part::Field(Field::from_name(name)?),
+                    });
+                    chars = rest[end + 1..].chars();
+                }
+                '}' => bail!("unmatched '}}' in status line"),
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            parts.push(Part::Text(text));
+        }
+        Ok(StatusLine { parts })
+    }
+
+    /// The text to show on the left and on the right.
+    pub fn expand(&self, window: &WindowData, buffer: &BufferData) -> (String, String) {
+        let mut left = String::new();
+        let mut right = String::new();
+        let mut aligned = false;
+        let mut drop_space = false;
+        for part in &self.parts {
+            let out = if aligned { &mut right } else { &mut left };
+            match part {
+                Part::Text(text) => {
+                    let text = match text.strip_prefix(' ') {
+                        Some(rest) if drop_space => rest,
+                        _ => text,
+                    };
+                    out.push_str(text);
+                    drop_space = false;
+                }
+                Part::Field(field) => {
+                    let value = value(*field, window, buffer);
+                    drop_space = value.is_empty();
+                    out.push_str(&value);
+                }
+                Part::Align => aligned = true,
+            }
+        }
+        (left, right)
+    }
+}
+
+fn value(field: Field, window: &WindowData, buffer: &BufferData) -> String {
+    match field {
+        Field::Name => buffer.name.clone(),
+        Field::Path => match &buffer.path {
+            Some(path) => path.display().to_string(),
+            None => String::new(),
+        },
+        Field::Modified if buffer.modified => String::from("[+]"),
+        Field::Modified => String::new(),
+        Field::Line => cursor(window, buffer).0.to_string(),
+        Field::Column => cursor(window, buffer).1.to_string(),
+        Field::Selections => match window.selections.len() {
+            1 => String::new(),
+            count => format!("{} sel", count),
+        },
+        Field::Percent => format!("{}%", percent(window, buffer)),
+        Field::Encoding if buffer.binary.is_some() => String::from("binary"),
+        Field::Encoding => buffer.encoding.name().to_owned(),
+        Field::LineEnding if buffer.binary.is_some() => String::new(),
+        Field::LineEnding => buffer.line_ending.name().to_owned(),
+        Field::FileInfo => file_info(buffer),
+        Field::FileType => {
+            let path = buffer.path.as_deref().unwrap_or(Path::new(&buffer.name));
+            file_type(path).unwrap_or_default().to_owned()
+        }
+    }
+}
+
+/// The format of the buffer's file, and for large files the page shown and
+/// how far line counting got.
+fn file_info(buffer: &BufferData) -> String {
+    if buffer.binary.is_some() {
+        return String::from("binary");
+    }
+    let format = format!("{} {}", buffer.encoding.name(), buffer.line_ending.name());
+    let large = match &buffer.large {
+        Some(large) => large,
+        None => return format,
+    };
+    let lines = match large.lines.total() {
+        Some(total) => format!("{} lines", total),
+        None => {
+            let scanned = large.lines.scanned_bytes.load(Ordering::Relaxed);
+            format!("counting lines {}%", scanned * 100 / large.len.max(1))
+        }
+    };
+    format!(
+        "{} page {}/{} {}",
+        format,
+        large.page + 1,
+        large.page_count(),
+        lines
+    )
+}
+
+/// The one-based line and column of the primary cursor. In the hex view
+/// these are the row and the byte in it. Lines of a large file are counted
+/// from the start of the file once the pages before are counted.
+fn cursor(window: &WindowData, buffer: &BufferData) -> (usize, usize) {
+    if buffer.binary.is_some() {
+        let cursor = window.hex_cursor;
+        return (cursor.row() + 1, cursor.offset % BYTES_PER_ROW + 1);
+    }
+    let selection = &window.selections[window.primary_selection];
+    let position = selection.end.position(&buffer.content);
+    let before = buffer
+        .large
+        .as_ref()
+        .map_or(0, LargeFile::lines_before_current_page);
+    (
+        before + position.line.one_based(),
+        position.column.one_based(),
+    )
+}
+
+fn percent(window: &WindowData, buffer: &BufferData) -> usize {
+    if let Some(bytes) = &buffer.binary {
+        return (window.hex_cursor.offset * 100 / bytes.len().max(1)).min(100);
+    }
+    let (line, _) = cursor(window, buffer);
+    let lines = match &buffer.large {
+        Some(large) => match large.lines.total() {
+            Some(total) => total,
+            // Until the lines are counted, go by the page.
+            None => return ((large.page + 1) * 100 / large.page_count()) as usize,
+        },
+        None => LineIndex::last_of(&buffer.content).one_based(),
+    };
+    (line * 100 / lines.max(1)).min(100)
+}
+
+/// The language of a file, from its name.
+pub fn file_type(path: &Path) -> Option<&'static str> {
+    let name = path.file_name()?.to_str()?;
+    let by_name = match name {
+        "Makefile" | "makefile" | "GNUmakefile" => Some("make"),
+        "Dockerfile" => Some("dockerfile"),
+        "Cargo.lock" => Some("toml"),
+        _ => None,
+    };
+    if by_name.is_some() {
+        return by_name;
+    }
+    Some(match path.extension()?.to_str()? {
+        "rs" => "rust",
+        "toml" => "toml",
+        "md" | "markdown" => "markdown",
+        "c" | "h" => "c",
+        "cc" | "cpp" | "cxx" | "hh" | "hpp" => "cpp",
+        "go" => "go",
+        "py" => "python",
+        "js" | "mjs" | "cjs" => "javascript",
+        "ts" => "typescript",
+        "json" => "json",
+        "yaml" | "yml" => "yaml",
+        "html" | "htm" => "html",
+        "css" => "css",
+        "sh" | "bash" => "sh",
+        "txt" => "text",
+        _ => return None,
+    })
+}
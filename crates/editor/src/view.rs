@@ -0,0 +1,218 @@
+//! Which part of a buffer a window shows. The view is measured in rows:
+//! lines when they don't wrap, and the rows `display::wrap` breaks them into
+//! when they do.
+
+use crate::{
+    display,
+    location::{CharIndex, LineIndex, Movement},
+    options::Options,
+    BufferData, WindowAction, WindowData,
+};
+use ropey::Rope;
+
+/// A line and a row of it, both zero-based, which order like the text.
+type RowPosition = (usize, usize);
+
+/// How the lines of a buffer are laid out in a text area `width` cells wide.
+struct Layout<'a> {
+    rope: &'a Rope,
+    options: &'a Options,
+    width: usize,
+}
+
+impl Layout<'_> {
+    fn rows(&self, line: usize) -> usize {
+        if !self.options.wrap {
+            return 1;
+        }
+        let text = LineIndex::from_zero_based(line)
+            .slice_of(self.rope)
+            .to_string();
+        let glyphs: Vec<_> = display::glyphs(&text, self.options.tab_width).collect();
+        display::wrap(&glyphs, self.width).len()
+    }
+
+    fn row_of(&self, char_idx: CharIndex) -> RowPosition {
+        let line = char_idx.line(self.rope);
+        if !self.options.wrap {
+            return (line.zero_based(), 0);
+        }
+        let text = line.slice_of(self.rope).to_string();
+        let glyphs: Vec<_> = display::glyphs(&text, self.options.tab_width).collect();
+        let rows = display::wrap(&glyphs, self.width);
+        let column = char_idx.0 - line.char_of(self.rope);
+        (line.zero_based(), display::row_of(&rows, &glyphs, column))
+    }
+
+    fn last(&self) -> RowPosition {
+        let line = LineIndex::last_of(self.rope).zero_based();
+        (line, self.rows(line) - 1)
+    }
+
+    /// The row `n` rows before `position`, or the first one.
+    fn back(&self, (mut line, mut row): RowPosition, mut n: usize) -> RowPosition {
+        loop {
+            let step = row.min(n);
+            row -= step;
+            n -= step;
+            if n == 0 || line == 0 {
+                return (line, row);
+            }
+            line -= 1;
+            n -= 1;
+            row = self.rows(line) - 1;
+        }
+    }
+
+    /// The row `n` rows after `position`, or the last one.
+    fn forward(&self, (mut line, mut row): RowPosition, mut n: usize) -> RowPosition {
+        let last_line = self.last().0;
+        loop {
+            let step = (self.rows(line) - 1 - row).min(n);
+            row += step;
+            n -= step;
+            if n == 0 || line >= last_line {
+                return (line, row);
+            }
+            line += 1;
+            n -= 1;
+            row = 0;
+        }
+    }
+}
+
+impl WindowData {
+    fn top_position(&self) -> RowPosition {
+        (self.top.zero_based(), self.top_row)
+    }
+
+    fn set_top(&mut self, (line, row): RowPosition) {
+        self.top = LineIndex::from_zero_based(line);
+        self.top_row = row;
+    }
+
+    /// Scrolls so that the end of the primary selection is shown in a text
+    /// area of `width` by `height` cells, with `scrolloff` rows around it
+    /// where the buffer has them. The size is kept for the view actions.
+    pub fn scroll_to_cursor(
+        &mut self,
+        buffer: &BufferData,
+        options: &Options,
+        width: usize,
+        height: usize,
+    ) {
+        self.view_size = Some((width, height));
+        let layout = Layout {
+            rope: &buffer.content,
+            options,
+            width,
+        };
+        let cursor = self.selections[self.primary_selection].end;
+        let last = layout.last();
+        let top_line = self.top.zero_based().min(last.0);
+        self.set_top((top_line, self.top_row.min(layout.rows(top_line) - 1)));
+        let scrolloff = options.scrolloff.min(height.saturating_sub(1) / 2);
+        let position = layout.row_of(cursor);
+        let max_top = layout.back(position, scrolloff);
+        let min_top = layout.back(layout.forward(position, scrolloff), height - 1);
+        self.set_top(self.top_position().max(min_top).min(max_top));
+        if !options.wrap {
+            self.scroll_sideways_to_cursor(&buffer.content, options, width);
+        } else {
+            self.left = 0;
+        }
+    }
+
+    fn scroll_sideways_to_cursor(&mut self, rope: &Rope, options: &Options, width: usize) {
+        let cursor = self.selections[self.primary_selection].end;
+        let line = cursor.line(rope);
+        let column = cursor.0 - line.char_of(rope);
+        let text = line.slice_of(rope).to_string();
+        let (start, end) = display::glyphs(&text, options.tab_width)
+            .find(|glyph| column < glyph.column + glyph.len)
+            .map_or((0, 1), |glyph| {
+                (glyph.screen_column, glyph.screen_column + glyph.width)
+            });
+        if start < self.left {
+            self.left = start;
+        } else if end > self.left + width {
+            self.left = end - width;
+        }
+    }
+
+    /// Keeps the hex cursor's row in a view of `height` rows.
+    pub fn scroll_to_hex_cursor(&mut self, height: usize) {
+        self.view_size = None;
+        let row = self.hex_cursor.row();
+        if row < self.top.zero_based() {
+            self.top = LineIndex::from_zero_based(row);
+        } else if row >= self.top.zero_based() + height {
+            self.top = LineIndex::from_zero_based(row + 1 - height);
+        }
+    }
+
+    /// Moves the view for the actions that don't edit or select, in the
+    /// size it was last drawn in. The cursor only moves when scrolling
+    /// would leave it outside the view.
+    pub fn perform_view_action(
+        &mut self,
+        buffer: &BufferData,
+        options: &Options,
+        action: WindowAction,
+    ) -> anyhow::Result<()> {
+        let (width, height) = match self.view_size {
+            Some(size) => size,
+            None => return Ok(()),
+        };
+        let layout = Layout {
+            rope: &buffer.content,
+            options,
+            width,
+        };
+        let scrolloff = options.scrolloff.min(height.saturating_sub(1) / 2);
+        let cursor = layout.row_of(self.selections[self.primary_selection].end);
+        let top = match action {
+            WindowAction::CenterView => layout.back(cursor, height.saturating_sub(1) / 2),
+            WindowAction::ViewCursorAtTop => layout.back(cursor, scrolloff),
+            WindowAction::ViewCursorAtBottom => {
+                layout.back(layout.forward(cursor, scrolloff), height - 1)
+            }
+            WindowAction::ScrollViewUp(n) => layout.back(self.top_position(), n),
+            WindowAction::ScrollViewDown(n) => {
+                let last_top = layout.back(layout.last(), height - 1);
+                layout.forward(self.top_position(), n).min(last_top)
+            }
+            _ => return Ok(()),
+        };
+        self.set_top(top);
+
+        let bottom = layout.forward(top, height - 1);
+        let first = match top {
+            (0, 0) => top,
+            _ => layout.forward(top, scrolloff),
+        };
+        let last = match bottom {
+            bottom if bottom == layout.last() => bottom,
+            _ => layout.back(bottom, scrolloff),
+        };
+        // Lines that start above the view can't hold the cursor.
+        let first_line = if first.1 == 0 { first.0 } else { first.0 + 1 };
+        let movement = if cursor < first && cursor.0 < first_line.min(last.0) {
+            Movement::Down(first_line.min(last.0) - cursor.0)
+        } else if cursor > last && cursor.0 > last.0 {
+            Movement::Up(cursor.0 - last.0)
+        } else {
+            return Ok(());
+        };
+        let tab_width = options.tab_width;
+        let selection = &mut self.selections[self.primary_selection];
+        selection.end.move_visually(
+            &buffer.content,
+            movement,
+            tab_width,
+            &mut selection.desired_column,
+        )?;
+        selection.start = selection.end;
+        Ok(())
+    }
+}
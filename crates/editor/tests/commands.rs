@@ -0,0 +1,176 @@
+use editor::{
+    location::Movement, perform_action, render::render, run_command, Action, BufferData,
+    EditorData, WindowAction,
+};
+use ropey::Rope;
+use std::{fs, path::PathBuf};
+
+/// An empty directory for one test to write files in.
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR"))
+        .join("commands")
+        .join(name);
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn current_buffer_name(editor: &EditorData) -> &str {
+    &editor.buffers[editor.windows[editor.focused_window()].buffer].name
+}
+
+fn current_buffer(editor: &EditorData) -> &BufferData {
+    &editor.buffers[editor.windows[editor.focused_window()].buffer]
+}
+
+/// The left side of the status line, where `[+]` goes.
+fn status(editor: &EditorData) -> String {
+    let window = &editor.windows[editor.focused_window()];
+    let buffer = &editor.buffers[window.buffer];
+    editor.options.status_line.expand(window, buffer).0
+}
+
+fn window_action(editor: &mut EditorData, action: WindowAction) {
+    perform_action(editor, Action::Window(action)).unwrap();
+}
+
+#[test]
+fn saveas_needs_force_to_overwrite_a_file() {
+    let dir = temp_dir("saveas");
+    let path = dir.join("existing");
+    fs::write(&path, "keep me\n").unwrap();
+    let path = path.to_str().unwrap();
+
+    let mut editor = EditorData::new();
+    assert!(run_command(&mut editor, &["saveas", path]).is_err());
+    assert_eq!(fs::read_to_string(path).unwrap(), "keep me\n");
+    assert_eq!(current_buffer_name(&editor), "scratch");
+
+    run_command(&mut editor, &["saveas!", path]).unwrap();
+    assert_eq!(fs::read_to_string(path).unwrap(), "\n");
+    assert_eq!(current_buffer_name(&editor), path);
+}
+
+#[test]
+fn deleting_a_buffer_does_not_show_the_buffer_list_instead() {
+    let mut editor = EditorData::new();
+    run_command(&mut editor, &["buffers"]).unwrap();
+    assert_eq!(current_buffer_name(&editor), "[buffers]");
+    run_command(&mut editor, &["buffer", "scratch"]).unwrap();
+    run_command(&mut editor, &["bdelete"]).unwrap();
+    assert_eq!(current_buffer_name(&editor), "scratch");
+}
+
+#[test]
+fn saveas_refuses_a_path_open_in_another_buffer() {
+    let dir = temp_dir("saveas-open");
+    let path = dir.join("open");
+    fs::write(&path, "open\n").unwrap();
+    let path = path.to_str().unwrap();
+
+    let mut editor = EditorData::new();
+    run_command(&mut editor, &["open", path]).unwrap();
+    run_command(&mut editor, &["buffer", "scratch"]).unwrap();
+    assert!(run_command(&mut editor, &["saveas!", path]).is_err());
+    assert_eq!(fs::read_to_string(path).unwrap(), "open\n");
+    assert_eq!(current_buffer_name(&editor), "scratch");
+}
+
+#[test]
+fn cycling_buffers_skips_the_buffer_list() {
+    let mut editor = EditorData::new();
+    editor
+        .buffers
+        .insert(BufferData::scratch("other", Rope::from("\n")));
+    run_command(&mut editor, &["buffers"]).unwrap();
+    run_command(&mut editor, &["bnext"]).unwrap();
+    assert_eq!(current_buffer_name(&editor), "scratch");
+    run_command(&mut editor, &["bnext"]).unwrap();
+    assert_eq!(current_buffer_name(&editor), "other");
+    run_command(&mut editor, &["bnext"]).unwrap();
+    assert_eq!(current_buffer_name(&editor), "scratch");
+    run_command(&mut editor, &["bprev"]).unwrap();
+    assert_eq!(current_buffer_name(&editor), "other");
+}
+
+#[test]
+fn a_shorter_buffer_list_keeps_selections_inside_it() {
+    let mut editor = EditorData::new();
+    for i in 0..10 {
+        let name = format!("buffer {}", i);
+        editor
+            .buffers
+            .insert(BufferData::scratch(&name, Rope::from("\n")));
+    }
+    // The list in two windows, one of them at its end.
+    run_command(&mut editor, &["buffers"]).unwrap();
+    run_command(&mut editor, &["split"]).unwrap();
+    perform_action(
+        &mut editor,
+        Action::Window(WindowAction::Move(Movement::FileEnd)),
+    )
+    .unwrap();
+    run_command(&mut editor, &["wincmd", "w"]).unwrap();
+    for i in 0..8 {
+        run_command(&mut editor, &["bdelete", &format!("buffer {}", i)]).unwrap();
+    }
+    run_command(&mut editor, &["buffers"]).unwrap();
+    render(&mut editor, 40, 12);
+}
+
+#[test]
+fn quit_refuses_unsaved_changes_unless_forced() {
+    let mut editor = EditorData::new();
+    window_action(&mut editor, WindowAction::InsertAtSelectionStart('x'));
+    assert!(current_buffer(&editor).modified);
+    assert!(run_command(&mut editor, &["q"]).is_err());
+    assert!(!editor.want_quit);
+    run_command(&mut editor, &["q!"]).unwrap();
+    assert!(editor.want_quit);
+}
+
+#[test]
+fn wq_writes_and_quits() {
+    let dir = temp_dir("wq");
+    let path = dir.join("a.txt");
+    fs::write(&path, "a\n").unwrap();
+
+    let mut editor = EditorData::new();
+    run_command(&mut editor, &["open", path.to_str().unwrap()]).unwrap();
+    window_action(&mut editor, WindowAction::InsertAtSelectionStart('x'));
+    assert!(status(&editor).contains("[+]"));
+    run_command(&mut editor, &["wq"]).unwrap();
+    assert!(!current_buffer(&editor).modified);
+    assert!(editor.want_quit);
+    assert_eq!(fs::read_to_string(&path).unwrap(), "xa\n");
+}
+
+#[test]
+fn editing_back_to_the_saved_text_clears_modified() {
+    // There is no undo yet, so the insertion is deleted by hand; undoing it
+    // would bring the content back to the saved text the same way.
+    let mut editor = EditorData::new();
+    window_action(&mut editor, WindowAction::InsertAtSelectionStart('x'));
+    assert!(status(&editor).contains("[+]"));
+    window_action(&mut editor, WindowAction::Move(Movement::FileStart));
+    window_action(&mut editor, WindowAction::Delete);
+    assert_eq!(current_buffer(&editor).content.to_string(), "\n");
+    assert!(!current_buffer(&editor).modified);
+    assert!(!status(&editor).contains("[+]"));
+
+    // The same goes for the line ending.
+    run_command(&mut editor, &["set", "ff=dos"]).unwrap();
+    assert!(current_buffer(&editor).modified);
+    run_command(&mut editor, &["set", "ff=unix"]).unwrap();
+    assert!(!current_buffer(&editor).modified);
+}
+
+#[test]
+fn tab_width_is_limited() {
+    let mut editor = EditorData::new();
+    assert!(run_command(&mut editor, &["set", "ts=0"]).is_err());
+    assert!(run_command(&mut editor, &["set", "ts=1000000000000"]).is_err());
+    assert_eq!(editor.options.tab_width, 4);
+    run_command(&mut editor, &["set", "ts=64"]).unwrap();
+    assert_eq!(editor.options.tab_width, 64);
+}
@@ -0,0 +1,118 @@
+use editor::diff::{diff, map_line, merge, Hunk};
+use proptest::prelude::*;
+
+fn lines(text: &str) -> Vec<String> {
+    text.split_inclusive('\n').map(String::from).collect()
+}
+
+fn hunk(old: std::ops::Range<usize>, new: std::ops::Range<usize>) -> Hunk {
+    Hunk { old, new }
+}
+
+/// Rebuilds the new sequence from the old one and the hunks.
+fn apply<T: Clone>(old: &[T], new: &[T], hunks: &[Hunk]) -> Vec<T> {
+    let mut result = Vec::new();
+    let mut pos = 0;
+    for hunk in hunks {
+        result.extend_from_slice(&old[pos..hunk.old.start]);
+        result.extend_from_slice(&new[hunk.new.clone()]);
+        pos = hunk.old.end;
+    }
+    result.extend_from_slice(&old[pos..]);
+    result
+}
+
+/// The length of the longest common subsequence, the slow way.
+fn lcs_len<T: PartialEq>(a: &[T], b: &[T]) -> usize {
+    let mut table = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for i in 0..a.len() {
+        for j in 0..b.len() {
+            table[i + 1][j + 1] = if a[i] == b[j] {
+                table[i][j] + 1
+            } else {
+                table[i][j + 1].max(table[i + 1][j])
+            };
+        }
+    }
+    table[a.len()][b.len()]
+}
+
+#[test]
+fn diffs_insertions_deletions_and_replacements() {
+    assert_eq!(diff(&["a", "c"], &["a", "b", "c"]), [hunk(1..1, 1..2)]);
+    assert_eq!(diff(&["a", "b", "c"], &["a", "c"]), [hunk(1..2, 1..1)]);
+    assert_eq!(diff(&["a", "b", "c"], &["a", "x", "c"]), [hunk(1..2, 1..2)]);
+    assert_eq!(
+        diff(&["a", "b", "c", "d"], &["x", "b", "c"]),
+        [hunk(0..1, 0..1), hunk(3..4, 3..3)]
+    );
+    assert_eq!(diff::<&str>(&["a"], &["a"]), []);
+    assert_eq!(diff::<&str>(&[], &["a"]), [hunk(0..0, 0..1)]);
+}
+
+#[test]
+fn maps_lines_around_an_edit() {
+    let hunks = diff(&["a", "b", "c", "d"], &["a", "x", "y", "c", "d"]);
+    assert_eq!(map_line(&hunks, 0), 0);
+    // A changed line moves to the start of what replaced it.
+    assert_eq!(map_line(&hunks, 1), 1);
+    assert_eq!(map_line(&hunks, 2), 3);
+    assert_eq!(map_line(&hunks, 3), 4);
+}
+
+#[test]
+fn merges_changes_that_do_not_overlap() {
+    let base = lines("a\nb\nc\nd\n");
+    let merged = merge(&base, &lines("a\nB\nc\nd\n"), &lines("a\nb\nc\nD\ne\n"));
+    assert_eq!(merged.conflicts, 0);
+    assert_eq!(merged.lines, lines("a\nB\nc\nD\ne\n"));
+}
+
+#[test]
+fn the_same_change_on_both_sides_is_not_a_conflict() {
+    let base = lines("a\nb\nc\n");
+    let changed = lines("a\nx\nc\n");
+    let merged = merge(&base, &changed, &changed);
+    assert_eq!(merged.conflicts, 0);
+    assert_eq!(merged.lines, changed);
+}
+
+#[test]
+fn conflicting_changes_are_kept_between_markers() {
+    let base = lines("a\nb\nc\n");
+    let merged = merge(&base, &lines("a\nX\nc\n"), &lines("a\nY\nc\n"));
+    assert_eq!(merged.conflicts, 1);
+    assert_eq!(
+        merged.lines,
+        lines("a\n<<<<<<< buffer\nX\n||||||| saved\nb\n=======\nY\n>>>>>>> disk\nc\n")
+    );
+}
+
+fn sequence() -> impl Strategy<Value = Vec<char>> {
+    prop::collection::vec(prop::sample::select(&['a', 'b', 'c'][..]), 0..20)
+}
+
+proptest! {
+    #[test]
+    fn diff_is_minimal_and_rebuilds_the_new_sequence(old in sequence(), new in sequence()) {
+        let hunks = diff(&old, &new);
+        prop_assert_eq!(apply(&old, &new, &hunks), new.clone());
+        let changed: usize = hunks.iter().map(|h| h.old.len() + h.new.len()).sum();
+        prop_assert_eq!(changed, old.len() + new.len() - 2 * lcs_len(&old, &new));
+        for pair in hunks.windows(2) {
+            prop_assert!(pair[0].old.end < pair[1].old.start, "{:?}", hunks);
+        }
+    }
+
+    #[test]
+    fn merging_one_sided_changes_takes_them(base in sequence(), ours in sequence()) {
+        let to_lines = |s: &[char]| s.iter().map(|c| format!("{}\n", c)).collect::<Vec<_>>();
+        let (base, ours) = (to_lines(&base), to_lines(&ours));
+        let merged = merge(&base, &ours, &base);
+        prop_assert_eq!(merged.conflicts, 0);
+        prop_assert_eq!(&merged.lines, &ours);
+        let merged = merge(&base, &base, &ours);
+        prop_assert_eq!(merged.conflicts, 0);
+        prop_assert_eq!(&merged.lines, &ours);
+    }
+}
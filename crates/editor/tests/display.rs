@@ -0,0 +1,124 @@
+use editor::display::{buffer_column, glyphs, screen_column};
+use proptest::prelude::*;
+
+/// The text, column, screen column and width of each glyph.
+fn layout(line: &str, tab_width: usize) -> Vec<(String, usize, usize, usize)> {
+    glyphs(line, tab_width)
+        .map(|glyph| {
+            (
+                glyph.text.into_owned(),
+                glyph.column,
+                glyph.screen_column,
+                glyph.width,
+            )
+        })
+        .collect()
+}
+
+fn texts(line: &str) -> Vec<String> {
+    glyphs(line, 4)
+        .map(|glyph| glyph.text.into_owned())
+        .collect()
+}
+
+#[test]
+fn tabs_reach_the_next_tab_stop() {
+    assert_eq!(
+        layout("a\tb\t\n", 4),
+        [
+            (String::from("a"), 0, 0, 1),
+            (String::from("   "), 1, 1, 3),
+            (String::from("b"), 2, 4, 1),
+            (String::from("   "), 3, 5, 3),
+            (String::from(" "), 4, 8, 1),
+        ]
+    );
+    assert_eq!(layout("\t", 8), [(" ".repeat(8), 0, 0, 8)]);
+}
+
+#[test]
+fn control_characters_are_escaped() {
+    assert_eq!(texts("\x01\x1b\x7f"), ["^A", "^[", "^?"]);
+    assert_eq!(texts("\u{85}"), ["<U+0085>"]);
+    assert_eq!(screen_column("\x01x", 1, 4), 2);
+}
+
+#[test]
+fn zero_width_clusters_are_escaped() {
+    // A combining mark on its own, and a zero width space.
+    assert_eq!(texts("\u{301}a"), ["<U+0301>", "a"]);
+    assert_eq!(texts("\u{200b}"), ["<U+200B>"]);
+    // Attached to a base char, the mark is part of its cluster.
+    assert_eq!(layout("e\u{301}x", 4)[1], (String::from("x"), 2, 1, 1));
+}
+
+#[test]
+fn wide_clusters_take_two_cells() {
+    assert_eq!(
+        layout("中a", 4),
+        [(String::from("中"), 0, 0, 2), (String::from("a"), 1, 2, 1)]
+    );
+    assert_eq!(layout("\u{2764}\u{fe0f}", 4)[0].3, 2);
+    // Both cells of the wide char belong to it.
+    assert_eq!(buffer_column("中a", 0, 4), 0);
+    assert_eq!(buffer_column("中a", 1, 4), 0);
+    assert_eq!(buffer_column("中a", 2, 4), 1);
+}
+
+#[test]
+fn columns_past_the_end_of_the_line() {
+    assert_eq!(screen_column("ab\n", 5, 4), 5);
+    assert_eq!(buffer_column("ab\n", 10, 4), 2);
+}
+
+const PIECES: &[&str] = &[
+    "a",
+    " ",
+    "\t",
+    "\x01",
+    "e\u{301}",
+    "\u{301}",
+    "\u{200b}",
+    "\u{4e2d}",
+    "\u{1f469}\u{200d}\u{1f467}",
+    "\u{2764}\u{fe0f}",
+];
+
+fn line() -> impl Strategy<Value = String> {
+    (
+        prop::collection::vec(prop::sample::select(PIECES), 0..30),
+        any::<bool>(),
+    )
+        .prop_map(|(pieces, newline)| {
+            let mut line = pieces.concat();
+            if newline {
+                line.push('\n');
+            }
+            line
+        })
+}
+
+proptest! {
+    #[test]
+    fn screen_and_buffer_columns_round_trip(line in line(), tab_width in 1..9usize) {
+        let glyphs: Vec<_> = glyphs(&line, tab_width).collect();
+        for glyph in &glyphs {
+            let screen = screen_column(&line, glyph.column, tab_width);
+            prop_assert_eq!(screen, glyph.screen_column);
+            prop_assert_eq!(buffer_column(&line, screen, tab_width), glyph.column);
+            // Every cell of the glyph maps back to it.
+            for cell in screen..screen + glyph.width {
+                prop_assert_eq!(buffer_column(&line, cell, tab_width), glyph.column);
+            }
+        }
+        // The glyphs cover the line without gaps.
+        let mut column = 0;
+        let mut screen = 0;
+        for glyph in &glyphs {
+            prop_assert_eq!((glyph.column, glyph.screen_column), (column, screen));
+            column += glyph.len;
+            screen += glyph.width;
+        }
+        prop_assert_eq!(column, line.chars().count());
+    }
+}
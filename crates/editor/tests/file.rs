@@ -0,0 +1,351 @@
+use editor::{
+    file::{read, write_atomic, write_text, LineEnding, ReadAs, TextEncoding},
+    large::{LargeFile, PAGE_SIZE},
+    options::Options,
+    BufferData,
+};
+use std::{fs, io::Write, path::PathBuf, thread, time::Duration};
+
+/// An empty directory for one test to write files in.
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR"))
+        .join("file")
+        .join(name);
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn write(path: &std::path::Path, backup: bool, contents: &str) {
+    write_atomic(path, backup, |writer: &mut dyn Write| {
+        writer.write_all(contents.as_bytes())
+    })
+    .unwrap();
+}
+
+#[test]
+fn writing_replaces_the_contents() {
+    let dir = temp_dir("replace");
+    let path = dir.join("a.txt");
+    write(&path, false, "first\n");
+    write(&path, false, "second\n");
+    assert_eq!(fs::read_to_string(&path).unwrap(), "second\n");
+    // No temporary files are left behind.
+    assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+}
+
+#[test]
+fn a_backup_keeps_the_previous_contents() {
+    let dir = temp_dir("backup");
+    let path = dir.join("a.txt");
+    fs::write(&path, "old\n").unwrap();
+    write(&path, true, "new\n");
+    assert_eq!(fs::read_to_string(&path).unwrap(), "new\n");
+    assert_eq!(fs::read_to_string(dir.join("a.txt~")).unwrap(), "old\n");
+}
+
+#[cfg(unix)]
+#[test]
+fn symlinks_are_followed() {
+    let dir = temp_dir("symlink");
+    let target = dir.join("target.txt");
+    let link = dir.join("link.txt");
+    fs::write(&target, "old\n").unwrap();
+    std::os::unix::fs::symlink(&target, &link).unwrap();
+    write(&link, false, "new\n");
+    assert!(fs::symlink_metadata(&link)
+        .unwrap()
+        .file_type()
+        .is_symlink());
+    assert_eq!(fs::read_to_string(&target).unwrap(), "new\n");
+}
+
+#[cfg(unix)]
+#[test]
+fn permissions_are_kept_and_set_before_writing() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = temp_dir("permissions");
+    let path = dir.join("secret.txt");
+    fs::write(&path, "old\n").unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).unwrap();
+
+    let mode = |path: &std::path::Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+    let mut temp_modes = Vec::new();
+    write_atomic(&path, false, |writer: &mut dyn Write| {
+        for entry in fs::read_dir(&dir).unwrap() {
+            let entry = entry.unwrap().path();
+            if entry != path {
+                temp_modes.push(mode(&entry));
+            }
+        }
+        writer.write_all(b"new\n")
+    })
+    .unwrap();
+    assert_eq!(temp_modes, [0o600]);
+    assert_eq!(mode(&path), 0o600);
+    assert_eq!(fs::read_to_string(&path).unwrap(), "new\n");
+}
+
+/// Reads `bytes` from a file, checks the text and encoding found, and that
+/// writing the text back gives the same bytes.
+fn round_trip(name: &str, bytes: &[u8], text: &str, encoding: &str, bom: bool) {
+    let dir = temp_dir(name);
+    let path = dir.join("a.txt");
+    fs::write(&path, bytes).unwrap();
+    let contents = read(&path, ReadAs::Detect).unwrap();
+    assert!(contents.binary.is_none());
+    assert_eq!(contents.text.to_string(), text);
+    assert_eq!(contents.encoding.name(), encoding);
+    assert_eq!(contents.encoding.bom, bom);
+
+    let mut written = Vec::new();
+    write_text(
+        &contents.text,
+        contents.line_ending,
+        contents.encoding,
+        &mut written,
+    )
+    .unwrap();
+    assert_eq!(written, bytes);
+}
+
+#[test]
+fn detects_utf8_with_and_without_a_bom() {
+    round_trip(
+        "utf8",
+        "h\u{e9}llo\n".as_bytes(),
+        "h\u{e9}llo\n",
+        "UTF-8",
+        false,
+    );
+    round_trip("utf8-bom", b"\xEF\xBB\xBFhi\n", "hi\n", "UTF-8", true);
+}
+
+#[test]
+fn detects_utf16_with_a_bom() {
+    round_trip(
+        "utf16le-bom",
+        b"\xFF\xFEh\0i\0\n\0",
+        "hi\n",
+        "UTF-16LE",
+        true,
+    );
+    round_trip(
+        "utf16be-bom",
+        b"\xFE\xFF\0h\0i\0\n",
+        "hi\n",
+        "UTF-16BE",
+        true,
+    );
+}
+
+#[test]
+fn detects_utf16_without_a_bom() {
+    round_trip(
+        "utf16le",
+        b"h\0e\0l\0l\0o\0\n\0",
+        "hello\n",
+        "UTF-16LE",
+        false,
+    );
+    round_trip(
+        "utf16be",
+        b"\0h\0e\0l\0l\0o\0\n",
+        "hello\n",
+        "UTF-16BE",
+        false,
+    );
+}
+
+#[test]
+fn falls_back_to_latin1() {
+    round_trip(
+        "latin1",
+        b"caf\xE9\r\n",
+        "caf\u{e9}\n",
+        "windows-1252",
+        false,
+    );
+}
+
+/// Reads `bytes` as UTF-8 text, checks the line ending found and the text,
+/// and that writing the text back gives the same bytes.
+fn line_ending_round_trip(name: &str, bytes: &str, text: &str, line_ending: LineEnding) {
+    let dir = temp_dir(name);
+    let path = dir.join("a.txt");
+    fs::write(&path, bytes).unwrap();
+    let contents = read(&path, ReadAs::Detect).unwrap();
+    assert_eq!(contents.line_ending, line_ending);
+    assert_eq!(contents.text.to_string(), text);
+
+    let mut written = Vec::new();
+    write_text(
+        &contents.text,
+        contents.line_ending,
+        contents.encoding,
+        &mut written,
+    )
+    .unwrap();
+    assert_eq!(String::from_utf8(written).unwrap(), bytes);
+}
+
+#[test]
+fn detects_line_endings() {
+    line_ending_round_trip("lf", "a\nb\n", "a\nb\n", LineEnding::Lf);
+    line_ending_round_trip("crlf", "a\r\nb\r\n", "a\nb\n", LineEnding::CrLf);
+    line_ending_round_trip("cr", "a\rb\r", "a\nb\n", LineEnding::Cr);
+    line_ending_round_trip("none", "a", "a", LineEnding::Lf);
+}
+
+#[test]
+fn stray_carriage_returns_are_kept() {
+    // They don't break the line either.
+    let text = ropey::Rope::from("a\rb\n\u{85}c\n");
+    assert_eq!(text.len_lines(), 3);
+
+    line_ending_round_trip("lf-cr", "a\rb\nc\r\n", "a\rb\nc\r\n", LineEnding::Lf);
+    line_ending_round_trip(
+        "crlf-cr",
+        "a\rb\r\nc\r\n\r",
+        "a\rb\nc\n\r",
+        LineEnding::CrLf,
+    );
+}
+
+#[test]
+fn mixed_line_endings_go_by_the_most_common() {
+    line_ending_round_trip("mixed-tie", "a\r\nb\nc\r", "a\r\nb\nc\r", LineEnding::Lf);
+    // A lone `\n` in a CRLF file is a line break, so it is written back as
+    // `\r\n`.
+    let dir = temp_dir("mixed-crlf");
+    let path = dir.join("a.txt");
+    fs::write(&path, "a\r\nb\r\nc\n").unwrap();
+    let contents = read(&path, ReadAs::Detect).unwrap();
+    assert_eq!(contents.line_ending, LineEnding::CrLf);
+    assert_eq!(contents.text.to_string(), "a\nb\nc\n");
+    let mut written = Vec::new();
+    write_text(
+        &contents.text,
+        contents.line_ending,
+        contents.encoding,
+        &mut written,
+    )
+    .unwrap();
+    assert_eq!(written, b"a\r\nb\r\nc\r\n");
+}
+
+/// Options that open every file page by page.
+fn large_files() -> Options {
+    Options {
+        large_file_threshold: 1,
+        ..Options::default()
+    }
+}
+
+#[test]
+fn large_files_keep_their_encoding_when_edited() {
+    let dir = temp_dir("large-latin1");
+    let path = dir.join("a.txt");
+    // More than one page, so untouched pages are copied as they are.
+    let mut bytes = Vec::new();
+    while bytes.len() < 3 << 20 {
+        bytes.extend_from_slice(b"caf\xE9 au lait\r\n");
+    }
+    fs::write(&path, &bytes).unwrap();
+
+    let options = large_files();
+    let mut buffer = BufferData::open(String::from("a.txt"), path.clone(), &options).unwrap();
+    assert!(buffer.large.is_some());
+    assert_eq!(buffer.encoding.name(), "windows-1252");
+    assert!(buffer
+        .content
+        .to_string()
+        .starts_with("caf\u{e9} au lait\n"));
+
+    buffer.content.insert_char(0, '\u{e8}');
+    buffer.write_to(&path, &options).unwrap();
+    let mut expected = b"\xE8".to_vec();
+    expected.extend_from_slice(&bytes);
+    assert!(fs::read(&path).unwrap() == expected);
+}
+
+#[test]
+fn large_files_keep_a_byte_order_mark() {
+    let dir = temp_dir("large-bom");
+    let path = dir.join("a.txt");
+    fs::write(&path, "\u{feff}h\u{e9}llo\n").unwrap();
+
+    let options = large_files();
+    let mut buffer = BufferData::open(String::from("a.txt"), path.clone(), &options).unwrap();
+    assert!(buffer.large.is_some());
+    assert!(buffer.encoding.bom);
+    assert_eq!(buffer.content.to_string(), "h\u{e9}llo\n");
+
+    buffer.content.insert(0, "oh ");
+    buffer.write_to(&path, &options).unwrap();
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        "\u{feff}oh h\u{e9}llo\n"
+    );
+}
+
+#[test]
+fn large_utf16_and_binary_files_are_read_whole() {
+    let dir = temp_dir("large-whole");
+    let options = large_files();
+
+    let path = dir.join("utf16.txt");
+    fs::write(&path, b"h\0i\0\n\0").unwrap();
+    let buffer = BufferData::open(String::from("utf16.txt"), path, &options).unwrap();
+    assert!(buffer.large.is_none());
+    assert_eq!(buffer.content.to_string(), "hi\n");
+
+    let path = dir.join("data.bin");
+    fs::write(&path, (0..=255).collect::<Vec<u8>>()).unwrap();
+    let buffer = BufferData::open(String::from("data.bin"), path, &options).unwrap();
+    assert!(buffer.large.is_none());
+    assert!(buffer.binary.is_some());
+}
+
+#[test]
+fn lines_longer_than_a_page_are_split_between_pages() {
+    let dir = temp_dir("large-long-line");
+    let path = dir.join("a.txt");
+    // Two-byte chars from an odd offset, so the page boundaries fall inside
+    // them.
+    let mut text = String::from("abcd\n");
+    text.push_str(&"\u{e9}".repeat(PAGE_SIZE as usize * 5 / 4));
+    text.push_str("\nend\n");
+    fs::write(&path, &text).unwrap();
+
+    let large = LargeFile::open(&path, TextEncoding::UTF_8).unwrap();
+    assert_eq!(large.page_count(), 3);
+    let pages: Vec<String> = (0..large.page_count())
+        .map(|page| large.read_page(page).unwrap().to_string())
+        .collect();
+    for page in &pages {
+        assert!(!page.is_empty());
+        assert!(page.len() <= 2 * PAGE_SIZE as usize);
+    }
+    assert!(pages.concat() == text);
+
+    while large.lines.total().is_none() {
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(large.lines.total(), Some(3));
+    let mut lines_before = 0;
+    for (page, text) in pages.iter().enumerate() {
+        assert_eq!(
+            large.lines.lines_before_page(page as u64),
+            Some(lines_before)
+        );
+        lines_before += text.matches('\n').count();
+    }
+
+    // Untouched pages are copied back as they were.
+    let mut written = Vec::new();
+    let current = large.read_page(0).unwrap();
+    large.write_to(&current, &mut written).unwrap();
+    assert!(written == text.as_bytes());
+}
@@ -0,0 +1,130 @@
+use editor::{
+    grapheme,
+    location::{CharIndex, Movement},
+    perform_action, Action, EditorData, WindowAction,
+};
+use proptest::prelude::*;
+use ropey::Rope;
+
+/// Pieces of text that are single clusters made of several chars, mixed with
+/// plain characters and combining marks that attach to whatever precedes them.
+const PIECES: &[&str] = &[
+    "a",
+    " ",
+    "\n",
+    "e\u{301}",
+    "x\u{308}\u{301}",
+    "\u{301}",
+    "\u{200d}",
+    "\u{1f469}\u{200d}\u{1f469}\u{200d}\u{1f467}",
+    "\u{1f1fa}\u{1f1f8}",
+    "\u{1f1eb}",
+    "\u{4e2d}",
+    "\u{1f44d}\u{1f3fd}",
+];
+
+fn text() -> impl Strategy<Value = String> {
+    prop::collection::vec(prop::sample::select(PIECES), 0..40).prop_map(|pieces| {
+        let mut text = pieces.concat();
+        text.push('\n');
+        text
+    })
+}
+
+fn movement() -> impl Strategy<Value = Movement> {
+    prop_oneof![
+        (1..4usize).prop_map(Movement::Left),
+        (1..4usize).prop_map(Movement::Right),
+        (1..3usize).prop_map(Movement::Up),
+        (1..3usize).prop_map(Movement::Down),
+        Just(Movement::LineStart),
+        Just(Movement::LineEnd),
+        Just(Movement::FileStart),
+        Just(Movement::FileEnd),
+    ]
+}
+
+/// Sequences of window actions as the TUI sends them for a key press.
+fn key() -> impl Strategy<Value = Vec<WindowAction>> {
+    prop_oneof![
+        movement().prop_map(|m| vec![WindowAction::Move(m)]),
+        movement().prop_map(|m| vec![WindowAction::ShiftEnd(m)]),
+        Just(vec![WindowAction::Delete]),
+        Just(vec![
+            WindowAction::Move(Movement::Left(1)),
+            WindowAction::Delete,
+        ]),
+        prop::sample::select(PIECES)
+            .prop_flat_map(|piece| prop::sample::select(piece.chars().collect::<Vec<_>>()))
+            .prop_map(|c| vec![
+                WindowAction::InsertAtSelectionStart(c),
+                WindowAction::ShiftStart(Movement::Right(1)),
+                WindowAction::ShiftEnd(Movement::Right(1)),
+            ]),
+    ]
+}
+
+fn assert_on_boundary(rope: &Rope, char_idx: CharIndex) {
+    assert!(
+        char_idx.0 < rope.len_chars(),
+        "{:?} is past the end",
+        char_idx
+    );
+    let position = char_idx.position(rope);
+    let line = position.line.slice_of(rope);
+    assert!(
+        grapheme::is_boundary(line, position.column.zero_based()),
+        "{:?} is inside a cluster of {:?}",
+        position,
+        line
+    );
+}
+
+proptest! {
+    #[test]
+    fn selections_never_end_inside_a_cluster(
+        text in text(),
+        keys in prop::collection::vec(key(), 0..30),
+    ) {
+        let mut editor = EditorData::new();
+        let window_id = editor.focused_window();
+        let buffer_id = editor.windows[window_id].buffer;
+        editor.buffers[buffer_id].content = Rope::from(text.as_str());
+        for actions in keys {
+            for action in actions {
+                // Running into the start or end of the buffer is fine.
+                _ = perform_action(&mut editor, Action::Window(action));
+            }
+            let rope = &editor.buffers[buffer_id].content;
+            for selection in editor.windows[window_id].selections.iter() {
+                assert_on_boundary(rope, selection.start);
+                assert_on_boundary(rope, selection.end);
+                let range = selection.range_of(rope);
+                prop_assert!(grapheme::is_boundary(rope.slice(..), range.start));
+                prop_assert!(grapheme::is_boundary(rope.slice(..), range.end));
+            }
+        }
+    }
+
+    #[test]
+    fn boundaries_match_unicode_segmentation(text in text()) {
+        use unicode_segmentation::UnicodeSegmentation;
+        let rope = Rope::from(text.as_str());
+        let slice = rope.slice(..);
+        let mut starts = Vec::new();
+        let mut offset = 0;
+        for cluster in text.graphemes(true) {
+            starts.push(offset);
+            offset += cluster.chars().count();
+        }
+        for (i, &start) in starts.iter().enumerate() {
+            let end = starts.get(i + 1).copied().unwrap_or(offset);
+            prop_assert_eq!(grapheme::next_boundary(slice, start), end);
+            prop_assert_eq!(grapheme::prev_boundary(slice, end), start);
+            for inside in start + 1..end {
+                prop_assert!(!grapheme::is_boundary(slice, inside));
+                prop_assert_eq!(grapheme::floor_boundary(slice, inside), start);
+            }
+        }
+    }
+}
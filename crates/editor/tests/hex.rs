@@ -0,0 +1,49 @@
+use editor::{
+    hex::{HexCursor, BYTES_PER_ROW},
+    location::Movement,
+    status::StatusLine,
+    EditorData,
+};
+
+#[test]
+fn file_end_is_the_append_position() {
+    let mut cursor = HexCursor::default();
+    cursor.move_to(40, Movement::FileEnd);
+    assert_eq!(cursor.offset, 40);
+    cursor.move_to(40, Movement::FileStart);
+    assert_eq!(cursor.offset, 0);
+}
+
+#[test]
+fn scrolling_keeps_the_cursor_row_in_view() {
+    let mut editor = EditorData::new();
+    let window_id = editor.focused_window();
+    let window = &mut editor.windows[window_id];
+    let height = 4;
+    for row in 0..12 {
+        window.hex_cursor.offset = row * BYTES_PER_ROW;
+        window.scroll_to_hex_cursor(height);
+        let top = window.top.zero_based();
+        assert!(top <= row && row < top + height, "row {} top {}", row, top);
+    }
+    // Moving down one row past the bottom scrolls by exactly one row.
+    assert_eq!(window.top.zero_based(), 11 + 1 - height);
+}
+
+#[test]
+fn percent_is_at_most_a_hundred() {
+    let mut editor = EditorData::new();
+    let window_id = editor.focused_window();
+    let buffer_id = editor.windows[window_id].buffer;
+    editor.buffers[buffer_id].binary = Some(vec![0; 40]);
+    let status = StatusLine::parse("{percent}").unwrap();
+    let mut percent = |offset| {
+        let window = &mut editor.windows[window_id];
+        window.hex_cursor.offset = offset;
+        status.expand(window, &editor.buffers[buffer_id]).0
+    };
+    assert_eq!(percent(0), "0%");
+    assert_eq!(percent(39), "97%");
+    // The append position after the last byte.
+    assert_eq!(percent(40), "100%");
+}
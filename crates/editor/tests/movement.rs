@@ -0,0 +1,146 @@
+mod reference;
+
+use editor::location::{CharIndex, ColumnIndex, LineIndex, Movement, Position};
+use proptest::prelude::*;
+use reference::Text;
+use ropey::Rope;
+
+const PIECES: &[&str] = &[
+    "a",
+    "b",
+    " ",
+    "\t",
+    "\n",
+    "\n",
+    "e\u{301}",
+    "\u{301}",
+    "\u{1f1fa}\u{1f1f8}",
+    "\u{1f469}\u{200d}\u{1f467}",
+    "\u{4e2d}",
+];
+
+fn text() -> impl Strategy<Value = String> {
+    (
+        prop::collection::vec(prop::sample::select(PIECES), 1..60),
+        any::<bool>(),
+    )
+        .prop_map(|(pieces, final_newline)| {
+            let mut text = pieces.concat();
+            if final_newline {
+                text.push('\n');
+            }
+            text
+        })
+}
+
+fn movement() -> impl Strategy<Value = Movement> {
+    prop_oneof![
+        (0..20usize).prop_map(Movement::Left),
+        (0..20usize).prop_map(Movement::Right),
+        (0..8usize).prop_map(Movement::Up),
+        (0..8usize).prop_map(Movement::Down),
+        Just(Movement::LineStart),
+        Just(Movement::LineEnd),
+        Just(Movement::FileStart),
+        Just(Movement::FileEnd),
+    ]
+}
+
+proptest! {
+    #[test]
+    fn movement_matches_stepping_one_at_a_time(
+        text in text(),
+        line in any::<prop::sample::Index>(),
+        column in 0..12usize,
+        movements in prop::collection::vec(movement(), 1..8),
+    ) {
+        let rope = Rope::from(text.as_str());
+        let text = Text::new(&text);
+        let line = LineIndex::from_zero_based(line.index(text.last_line().one_based()));
+        let mut expected = Position {
+            line,
+            column: ColumnIndex::from_zero_based(column),
+        };
+        reference::validate(&mut expected, &text);
+        let mut actual = CharIndex(text.char_index(expected));
+        for movement in movements {
+            let expected_result = reference::move_to(&mut expected, &text, movement);
+            let actual_result = actual.move_to(&rope, movement);
+            prop_assert_eq!(
+                format!("{:?}", actual_result),
+                format!("{:?}", expected_result),
+                "{:?} from {:?}",
+                movement,
+                actual
+            );
+            prop_assert_eq!(actual.0, text.char_index(expected), "{:?}", movement);
+            prop_assert_eq!(actual.position(&rope), expected, "{:?}", movement);
+        }
+    }
+}
+
+/// Moves down through `text` from `start`, one line at a time as a window
+/// does, and returns the column of each line landed on.
+fn columns_moving_down(text: &str, start: usize, tab_width: usize) -> Vec<usize> {
+    let rope = Rope::from(text);
+    let mut cursor = CharIndex(start);
+    let mut desired_column = None;
+    let mut columns = Vec::new();
+    while cursor
+        .move_visually(&rope, Movement::Down(1), tab_width, &mut desired_column)
+        .is_ok()
+    {
+        columns.push(cursor.position(&rope).column.zero_based());
+    }
+    columns
+}
+
+#[test]
+fn moving_down_keeps_the_column_across_a_shorter_line() {
+    assert_eq!(
+        columns_moving_down("abcdefgh\nab\nabcdefgh\n", 6, 4),
+        [2, 6]
+    );
+    // Back up again.
+    let rope = Rope::from("abcdefgh\nab\nabcdefgh\n");
+    let mut cursor = CharIndex(9 + 3 + 6);
+    let mut desired_column = None;
+    for line in [1, 0] {
+        cursor
+            .move_visually(&rope, Movement::Up(1), 4, &mut desired_column)
+            .unwrap();
+        assert_eq!(cursor.position(&rope).line.zero_based(), line);
+    }
+    assert_eq!(cursor.position(&rope).column.zero_based(), 6);
+}
+
+#[test]
+fn the_kept_column_is_on_the_screen() {
+    // The `x` after a tab is in screen column 4, as is the one after four
+    // chars, and the one after two wide chars.
+    assert_eq!(
+        columns_moving_down("\tx\nab\nabcdx\n\u{4e2d}\u{4e2d}x\n", 1, 4),
+        [2, 4, 2]
+    );
+    // Inside a tab or a wide char, the cursor goes onto it.
+    assert_eq!(columns_moving_down("abcx\n\tx\n", 3, 4), [0]);
+    assert_eq!(columns_moving_down("abcx\n\u{4e2d}\u{4e2d}x\n", 3, 4), [1]);
+}
+
+#[test]
+fn other_movements_forget_the_kept_column() {
+    let rope = Rope::from("abcdefgh\nab\nabcdefgh\n");
+    let mut cursor = CharIndex(6);
+    let mut desired_column = None;
+    cursor
+        .move_visually(&rope, Movement::Down(1), 4, &mut desired_column)
+        .unwrap();
+    cursor
+        .move_visually(&rope, Movement::Left(1), 4, &mut desired_column)
+        .unwrap();
+    assert_eq!(desired_column, None);
+    cursor
+        .move_visually(&rope, Movement::Down(1), 4, &mut desired_column)
+        .unwrap();
+    assert_eq!(cursor.position(&rope).column.zero_based(), 1);
+}
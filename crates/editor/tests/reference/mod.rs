@@ -0,0 +1,202 @@
+//! The movement code as it was before it did arithmetic on char indices,
+//! stepping one cluster or line at a time on a line and column. Kept to
+//! check and benchmark the new code against, so it works on its own copy of
+//! the text, split into lines and clusters, rather than on the rope.
+
+use editor::location::{ColumnIndex, LineIndex, Movement, MovementError, Position};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Text split into lines as ropey splits it: after every `\n`, with an empty
+/// line after a final one.
+pub struct Text {
+    lines: Vec<Line>,
+}
+
+struct Line {
+    /// The column each cluster starts at, in chars.
+    clusters: Vec<usize>,
+    /// The length in chars, with the newline.
+    len: usize,
+}
+
+impl Text {
+    pub fn new(text: &str) -> Text {
+        let mut lines: Vec<Line> = text.split_inclusive('\n').map(Line::new).collect();
+        if text.is_empty() || text.ends_with('\n') {
+            lines.push(Line::new(""));
+        }
+        Text { lines }
+    }
+
+    fn line(&self, line: LineIndex) -> &Line {
+        &self.lines[line.zero_based()]
+    }
+
+    /// The last line that has any chars.
+    pub fn last_line(&self) -> LineIndex {
+        let last = self.lines.len() - 1;
+        if self.lines[last].len == 0 && last > 0 {
+            LineIndex::from_zero_based(last - 1)
+        } else {
+            LineIndex::from_zero_based(last)
+        }
+    }
+
+    /// The index of the char at `position` in the whole text.
+    #[allow(dead_code)] // Not used by the benchmarks.
+    pub fn char_index(&self, position: Position) -> usize {
+        let before: usize = self.lines[..position.line.zero_based()]
+            .iter()
+            .map(|line| line.len)
+            .sum();
+        before + position.column.zero_based()
+    }
+}
+
+impl Line {
+    fn new(text: &str) -> Line {
+        let mut clusters = Vec::new();
+        let mut len = 0;
+        for cluster in text.graphemes(true) {
+            clusters.push(len);
+            len += cluster.chars().count();
+        }
+        Line { clusters, len }
+    }
+
+    /// The start of the cluster before the one at `column`.
+    fn prev_boundary(&self, column: usize) -> usize {
+        let i = self.clusters.partition_point(|&start| start < column);
+        if i == 0 {
+            0
+        } else {
+            self.clusters[i - 1]
+        }
+    }
+
+    /// The start of the cluster after the one at `column`, or the length.
+    fn next_boundary(&self, column: usize) -> usize {
+        let i = self.clusters.partition_point(|&start| start <= column);
+        self.clusters.get(i).copied().unwrap_or(self.len)
+    }
+
+    /// The start of the cluster `column` is in.
+    fn floor_boundary(&self, column: usize) -> usize {
+        self.prev_boundary(column + 1)
+    }
+}
+
+pub fn move_to(
+    position: &mut Position,
+    text: &Text,
+    movement: Movement,
+) -> Result<(), MovementError> {
+    match movement {
+        Movement::Left(n) => {
+            if n == 0 {
+                return Ok(());
+            }
+            let mut moved = false;
+            for _ in 0..n {
+                validate(position, text);
+                if position.column.is_first() {
+                    if !position.line.is_first() {
+                        move_to(position, text, Movement::Up(1))?;
+                        move_to(position, text, Movement::LineEnd)?;
+                        moved = true;
+                    } else {
+                        return Err(MovementError::NoPrevLine);
+                    }
+                } else {
+                    let line = text.line(position.line);
+                    let column = line.prev_boundary(position.column.zero_based());
+                    position.column = ColumnIndex::from_zero_based(column);
+                    moved = true;
+                }
+            }
+            if !moved {
+                return Err(MovementError::NoPrevLine);
+            }
+        }
+        Movement::Right(n) => {
+            if n == 0 {
+                return Ok(());
+            }
+            let mut moved = false;
+            for _ in 0..n {
+                validate(position, text);
+                let line = text.line(position.line);
+                let next = line.next_boundary(position.column.zero_based());
+                if next == line.len {
+                    move_to(position, text, Movement::Down(1))?;
+                    move_to(position, text, Movement::LineStart)?;
+                    moved = true;
+                } else {
+                    position.column = ColumnIndex::from_zero_based(next);
+                    moved = true;
+                }
+            }
+            if !moved {
+                return Err(MovementError::NoNextLine);
+            }
+        }
+        Movement::Down(n) => {
+            if n == 0 {
+                return Ok(());
+            }
+            let mut moved = false;
+            for _ in 0..n {
+                let next = position.line.zero_based() + 1;
+                if next < text.lines.len() && text.lines[next].len > 0 {
+                    position.line = LineIndex::from_zero_based(next);
+                    moved = true;
+                } else {
+                    break;
+                }
+            }
+            if !moved {
+                return Err(MovementError::NoNextLine);
+            }
+            validate(position, text);
+        }
+        Movement::Up(n) => {
+            if n == 0 {
+                return Ok(());
+            }
+            let n = n.min(position.line.zero_based());
+            if n == 0 {
+                return Err(MovementError::NoPrevLine);
+            }
+            position.line = LineIndex::from_zero_based(position.line.zero_based() - n);
+            validate(position, text);
+        }
+        Movement::LineStart => position.column = ColumnIndex::from_one_based(1),
+        Movement::LineEnd => {
+            position.column = ColumnIndex::from_zero_based(usize::MAX / 2);
+            validate(position, text);
+        }
+        Movement::FileStart => *position = Position::file_start(),
+        Movement::FileEnd => {
+            *position = Position {
+                line: text.last_line(),
+                column: ColumnIndex::from_one_based(1),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Moves onto the start of a cluster inside the text: onto the last line
+/// with any chars if the line is past it, and onto the last cluster of the
+/// line if the column is past its end.
+pub fn validate(position: &mut Position, text: &Text) {
+    position.line = position.line.min(text.last_line());
+    let line = text.line(position.line);
+    let column = position.column.zero_based();
+    let column = if column >= line.len {
+        line.prev_boundary(line.len)
+    } else {
+        line.floor_boundary(column)
+    };
+    position.column = ColumnIndex::from_zero_based(column);
+}
@@ -0,0 +1,166 @@
+use editor::{
+    display::{glyphs, wrap},
+    location::CharIndex,
+    options::Options,
+    EditorData, WindowAction,
+};
+use ropey::Rope;
+
+/// An editor showing `text`, with the cursor at `cursor`.
+fn showing(text: &str, cursor: usize) -> EditorData {
+    let mut editor = EditorData::new();
+    let window_id = editor.focused_window();
+    let buffer_id = editor.windows[window_id].buffer;
+    editor.buffers[buffer_id].content = Rope::from(text);
+    move_cursor(&mut editor, cursor);
+    editor
+}
+
+fn move_cursor(editor: &mut EditorData, char_idx: usize) {
+    let window_id = editor.focused_window();
+    let window = &mut editor.windows[window_id];
+    let selection = &mut window.selections[window.primary_selection];
+    selection.start = CharIndex(char_idx);
+    selection.end = CharIndex(char_idx);
+}
+
+fn view_options(wrap: bool, scrolloff: usize) -> Options {
+    Options {
+        wrap,
+        scrolloff,
+        ..Options::default()
+    }
+}
+
+/// Scrolls a `width` by `height` view to the cursor and returns the top
+/// line, the rows of it scrolled past and the first column shown.
+fn scroll(
+    editor: &mut EditorData,
+    options: &Options,
+    size: (usize, usize),
+) -> (usize, usize, usize) {
+    let window_id = editor.focused_window();
+    let window = &mut editor.windows[window_id];
+    let buffer = &editor.buffers[window.buffer];
+    window.scroll_to_cursor(buffer, options, size.0, size.1);
+    (window.top.zero_based(), window.top_row, window.left)
+}
+
+/// Performs a view action and returns the top line and the cursor's line.
+fn view_action(editor: &mut EditorData, options: &Options, action: WindowAction) -> (usize, usize) {
+    let window_id = editor.focused_window();
+    let window = &mut editor.windows[window_id];
+    let buffer = &editor.buffers[window.buffer];
+    window.perform_view_action(buffer, options, action).unwrap();
+    let cursor = window.selections[window.primary_selection].end;
+    (
+        window.top.zero_based(),
+        cursor.line(&buffer.content).zero_based(),
+    )
+}
+
+/// A hundred lines of two chars each, with their newlines.
+fn numbered_lines() -> String {
+    (0..100).map(|i| format!("{:02}\n", i)).collect()
+}
+
+#[test]
+fn scrolloff_keeps_rows_around_the_cursor() {
+    let options = view_options(true, 3);
+    let mut editor = showing(&numbered_lines(), 0);
+    assert_eq!(scroll(&mut editor, &options, (20, 10)), (0, 0, 0));
+    // Near the start of the file there are no rows above to keep.
+    move_cursor(&mut editor, 2 * 3);
+    assert_eq!(scroll(&mut editor, &options, (20, 10)).0, 0);
+    // Going down, the cursor stays three rows above the bottom.
+    move_cursor(&mut editor, 20 * 3);
+    assert_eq!(scroll(&mut editor, &options, (20, 10)).0, 20 + 3 - 9);
+    // Going up, three rows below the top.
+    move_cursor(&mut editor, 12 * 3);
+    assert_eq!(scroll(&mut editor, &options, (20, 10)).0, 12 - 3);
+    // Near the end there are no rows below to keep.
+    move_cursor(&mut editor, 99 * 3);
+    assert_eq!(scroll(&mut editor, &options, (20, 10)).0, 99 - 9);
+    move_cursor(&mut editor, 97 * 3);
+    assert_eq!(scroll(&mut editor, &options, (20, 10)).0, 99 - 9);
+}
+
+#[test]
+fn wrapped_rows_at_the_bottom_edge_are_scrolled_into_view() {
+    let options = view_options(true, 0);
+    let long = "x".repeat(35);
+    let rows = wrap(&glyphs(&format!("{}\n", long), 4).collect::<Vec<_>>(), 10).len();
+    assert!(rows >= 3);
+
+    // Five short lines, then the long one at the bottom of the view.
+    let text = format!("a\nb\nc\nd\ne\n{}\nf\n", long);
+    let end_of_long = 10 + long.len();
+    let mut editor = showing(&text, end_of_long);
+    let height = 6;
+    // The last row of the long line is the bottom row.
+    let top = 5 + rows - height;
+    assert_eq!(scroll(&mut editor, &options, (10, height)), (top, 0, 0));
+
+    // The view can start inside a wrapped line.
+    let text = format!("{}\nb\n", long);
+    let mut editor = showing(&text, long.len() + 1);
+    assert_eq!(scroll(&mut editor, &options, (10, 2)), (0, rows - 1, 0));
+    // And goes back to its first row for the cursor at its start.
+    move_cursor(&mut editor, 0);
+    assert_eq!(scroll(&mut editor, &options, (10, 2)), (0, 0, 0));
+}
+
+#[test]
+fn nowrap_scrolls_sideways_to_the_cursor() {
+    let options = view_options(false, 0);
+    let mut editor = showing(&format!("{}\n", "x".repeat(50)), 25);
+    // Just far enough that the cursor is in the last column.
+    assert_eq!(scroll(&mut editor, &options, (10, 5)).2, 26 - 10);
+    move_cursor(&mut editor, 5);
+    assert_eq!(scroll(&mut editor, &options, (10, 5)).2, 5);
+
+    // All of a wide char or a tab is shown.
+    let mut editor = showing(&format!("{}\n", "\u{4e2d}".repeat(10)), 7);
+    assert_eq!(scroll(&mut editor, &options, (5, 5)).2, 16 - 5);
+    let mut editor = showing("ab\tx\n", 2);
+    // The tab after two chars reaches the fourth column.
+    assert_eq!(scroll(&mut editor, &options, (3, 5)).2, 4 - 3);
+
+    // Wrapped lines are never scrolled sideways.
+    let mut editor = showing(&format!("{}\n", "x".repeat(50)), 25);
+    scroll(&mut editor, &options, (10, 5));
+    assert_eq!(scroll(&mut editor, &view_options(true, 0), (10, 5)).2, 0);
+}
+
+#[test]
+fn view_actions_put_the_cursor_line_in_place() {
+    let options = view_options(true, 3);
+    let mut editor = showing(&numbered_lines(), 50 * 3);
+    scroll(&mut editor, &options, (20, 10));
+
+    // zz, zt and zb, keeping the cursor where it is.
+    assert_eq!(
+        view_action(&mut editor, &options, WindowAction::CenterView),
+        (50 - 4, 50)
+    );
+    assert_eq!(
+        view_action(&mut editor, &options, WindowAction::ViewCursorAtTop),
+        (50 - 3, 50)
+    );
+    assert_eq!(
+        view_action(&mut editor, &options, WindowAction::ViewCursorAtBottom),
+        (50 + 3 - 9, 50)
+    );
+
+    // Near the start of the file the view can't go further up.
+    let mut editor = showing(&numbered_lines(), 3);
+    scroll(&mut editor, &options, (20, 10));
+    assert_eq!(
+        view_action(&mut editor, &options, WindowAction::CenterView),
+        (0, 1)
+    );
+    assert_eq!(
+        view_action(&mut editor, &options, WindowAction::ViewCursorAtBottom),
+        (0, 1)
+    );
+}
@@ -0,0 +1,136 @@
+//! The built-in font: five by nine pixel glyphs for printable ASCII, drawn in
+//! cells of six by ten pixels. It needs no font files, so it looks the same
+//! everywhere, which is what screenshots in tests want.
+
+pub const CELL_WIDTH: usize = 6;
+pub const CELL_HEIGHT: usize = 10;
+
+/// The glyphs from `' '` to `'~'`, row by row. The first seven rows stand on
+/// the baseline and the last two are for descenders.
+const GLYPHS: [&str; 95] = [
+    "..... ..... ..... ..... ..... ..... ..... ..... .....",
+    "..#.. ..#.. ..#.. ..#.. ..#.. ..... ..#.. ..... .....",
+    ".#.#. .#.#. .#.#. ..... ..... ..... ..... ..... .....",
+    ".#.#. .#.#. ##### .#.#. ##### .#.#. .#.#. ..... .....",
+    "..#.. .#### #.#.. .###. ..#.# ####. ..#.. ..... .....",
+    "##... ##..# ...#. ..#.. .#... #..## ...## ..... .....",
+    ".##.. #..#. #.#.. .#... #.#.# #..#. .##.# ..... .....",
+    "..#.. ..#.. .#... ..... ..... ..... ..... ..... .....",
+    "...#. ..#.. .#... .#... .#... ..#.. ...#. ..... .....",
+    ".#... ..#.. ...#. ...#. ...#. ..#.. .#... ..... .....",
+    "..... ..#.. #.#.# .###. #.#.# ..#.. ..... ..... .....",
+    "..... ..#.. ..#.. ##### ..#.. ..#.. ..... ..... .....",
+    "..... ..... ..... ..... ..... .##.. ..#.. .#... .....",
+    "..... ..... ..... ##### ..... ..... ..... ..... .....",
+    "..... ..... ..... ..... ..... .##.. .##.. ..... .....",
+    "..... ....# ...#. ..#.. .#... #.... ..... ..... .....",
+    ".###. #...# #..## #.#.# ##..# #...# .###. ..... .....",
+    "..#.. .##.. ..#.. ..#.. ..#.. ..#.. .###. ..... .....",
+    ".###. #...# ....# ...#. ..#.. .#... ##### ..... .....",
+    "##### ...#. ..#.. ...#. ....# #...# .###. ..... .....",
+    "...#. ..##. .#.#. #..#. ##### ...#. ...#. ..... .....",
+    "##### #.... ####. ....# ....# #...# .###. ..... .....",
+    "..##. .#... #.... ####. #...# #...# .###. ..... .....",
+    "##### ....# ...#. ..#.. .#... .#... .#... ..... .....",
+    ".###. #...# #...# .###. #...# #...# .###. ..... .....",
+    ".###. #...# #...# .#### ....# ...#. .##.. ..... .....",
+    "..... .##.. .##.. ..... .##.. .##.. ..... ..... .....",
+    "..... .##.. .##.. ..... .##.. .##.. ..#.. .#... .....",
+    "...#. ..#.. .#... #.... .#... ..#.. ...#. ..... .....",
+    "..... ..... ##### ..... ##### ..... ..... ..... .....",
+    ".#... ..#.. ...#. ....# ...#. ..#.. .#... ..... .....",
+    ".###. #...# ....# ...#. ..#.. ..... ..#.. ..... .....",
+    ".###. #...# ....# .##.# #.#.# #.#.# .###. ..... .....",
+    ".###. #...# #...# #...# ##### #...# #...# ..... .....",
+    "####. #...# #...# ####. #...# #...# ####. ..... .....",
+    ".###. #...# #.... #.... #.... #...# .###. ..... .....",
+    "###.. #..#. #...# #...# #...# #..#. ###.. ..... .....",
+    "##### #.... #.... ####. #.... #.... ##### ..... .....",
+    "##### #.... #.... ####. #.... #.... #.... ..... .....",
+    ".###. #...# #.... #.### #...# #...# .#### ..... .....",
+    "#...# #...# #...# ##### #...# #...# #...# ..... .....",
+    ".###. ..#.. ..#.. ..#.. ..#.. ..#.. .###. ..... .....",
+    "..### ...#. ...#. ...#. ...#. #..#. .##.. ..... .....",
+    "#...# #..#. #.#.. ##... #.#.. #..#. #...# ..... .....",
+    "#.... #.... #.... #.... #.... #.... ##### ..... .....",
+    "#...# ##.## #.#.# #.#.# #...# #...# #...# ..... .....",
+    "#...# #...# ##..# #.#.# #..## #...# #...# ..... .....",
+    ".###. #...# #...# #...# #...# #...# .###. ..... .....",
+    "####. #...# #...# ####. #.... #.... #.... ..... .....",
+    ".###. #...# #...# #...# #.#.# #..#. .##.# ..... .....",
+    "####. #...# #...# ####. #.#.. #..#. #...# ..... .....",
+    ".#### #.... #.... .###. ....# ....# ####. ..... .....",
+    "##### ..#.. ..#.. ..#.. ..#.. ..#.. ..#.. ..... .....",
+    "#...# #...# #...# #...# #...# #...# .###. ..... .....",
+    "#...# #...# #...# #...# #...# .#.#. ..#.. ..... .....",
+    "#...# #...# #...# #.#.# #.#.# #.#.# .#.#. ..... .....",
+    "#...# #...# .#.#. ..#.. .#.#. #...# #...# ..... .....",
+    "#...# #...# .#.#. ..#.. ..#.. ..#.. ..#.. ..... .....",
+    "##### ....# ...#. ..#.. .#... #.... ##### ..... .....",
+    ".###. .#... .#... .#... .#... .#... .###. ..... .....",
+    "..... #.... .#... ..#.. ...#. ....# ..... ..... .....",
+    ".###. ...#. ...#. ...#. ...#. ...#. .###. ..... .....",
+    "..#.. .#.#. #...# ..... ..... ..... ..... ..... .....",
+    "..... ..... ..... ..... ..... ..... ..... ##### .....",
+    ".#... ..#.. ...#. ..... ..... ..... ..... ..... .....",
+    "..... ..... .###. ....# .#### #...# .#### ..... .....",
+    "#.... #.... #.##. ##..# #...# #...# ####. ..... .....",
+    "..... ..... .###. #.... #.... #...# .###. ..... .....",
+    "....# ....# .##.# #..## #...# #...# .#### ..... .....",
+    "..... ..... .###. #...# ##### #.... .###. ..... .....",
+    "..##. .#..# .#... ###.. .#... .#... .#... ..... .....",
+    "..... ..... .#### #...# #...# #...# .#### ....# .###.",
+    "#.... #.... #.##. ##..# #...# #...# #...# ..... .....",
+    "..#.. ..... .##.. ..#.. ..#.. ..#.. .###. ..... .....",
+    "...#. ..... ..##. ...#. ...#. ...#. ...#. #..#. .##..",
+    "#.... #.... #..#. #.#.. ##... #.#.. #..#. ..... .....",
+    ".##.. ..#.. ..#.. ..#.. ..#.. ..#.. .###. ..... .....",
+    "..... ..... ##.#. #.#.# #.#.# #.#.# #.#.# ..... .....",
+    "..... ..... #.##. ##..# #...# #...# #...# ..... .....",
+    "..... ..... .###. #...# #...# #...# .###. ..... .....",
+    "..... ..... ####. #...# #...# #...# ####. #.... #....",
+    "..... ..... .#### #...# #...# #...# .#### ....# ....#",
+    "..... ..... #.##. ##..# #.... #.... #.... ..... .....",
+    "..... ..... .###. #.... .###. ....# ####. ..... .....",
+    ".#... .#... ###.. .#... .#... .#..# ..##. ..... .....",
+    "..... ..... #...# #...# #...# #..## .##.# ..... .....",
+    "..... ..... #...# #...# #...# .#.#. ..#.. ..... .....",
+    "..... ..... #...# #...# #.#.# #.#.# .#.#. ..... .....",
+    "..... ..... #...# .#.#. ..#.. .#.#. #...# ..... .....",
+    "..... ..... #...# #...# #...# #...# .#### ....# .###.",
+    "..... ..... ##### ...#. ..#.. .#... ##### ..... .....",
+    "..##. .#... .#... #.... .#... .#... ..##. ..... .....",
+    "..#.. ..#.. ..#.. ..#.. ..#.. ..#.. ..#.. ..... .....",
+    ".##.. ...#. ...#. ....# ...#. ...#. .##.. ..... .....",
+    "..... ..... .#... #.#.# ...#. ..... ..... ..... .....",
+];
+
+/// Glyphs past ASCII that the editor draws itself.
+const EXTRA: &[(char, &str)] = &[
+    // The marker at the start of wrapped rows.
+    (
+        '\u{21aa}',
+        "..... #.... #..#. .#### ...#. ..... ..... ..... .....",
+    ),
+];
+
+/// The pixels of `c` that are set, relative to the top left of its cell.
+pub fn pixels(c: char) -> Option<Vec<(usize, usize)>> {
+    // Borders run through the whole cell to meet the next one.
+    match c {
+        '\u{2500}' => return Some((0..CELL_WIDTH).map(|x| (x, CELL_HEIGHT / 2)).collect()),
+        '\u{2502}' => return Some((0..CELL_HEIGHT).map(|y| (CELL_WIDTH / 2, y)).collect()),
+        _ => {}
+    }
+    let glyph = match (c as usize).checked_sub(' ' as usize) {
+        Some(index) if index < GLYPHS.len() => GLYPHS[index],
+        _ => EXTRA.iter().find(|(extra, _)| *extra == c)?.1,
+    };
+    let pixels = glyph.split(' ').enumerate().flat_map(|(y, row)| {
+        row.bytes()
+            .enumerate()
+            .filter(|&(_, pixel)| pixel == b'#')
+            .map(move |(x, _)| (x, y + 1))
+    });
+    Some(pixels.collect())
+}
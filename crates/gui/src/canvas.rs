@@ -0,0 +1,77 @@
+//! A picture in memory that the frontend paints into, and shows in a window
+//! or writes out as PNG.
+
+use anyhow::Result;
+use std::io::Write;
+
+/// Pixels as `0x00RRGGBB`, row by row from the top left, which is the format
+/// windows take them in.
+pub struct Canvas {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u32>,
+}
+
+impl Canvas {
+    pub fn new(width: usize, height: usize) -> Canvas {
+        Canvas {
+            width,
+            height,
+            pixels: vec![0; width * height],
+        }
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> u32 {
+        self.pixels[y * self.width + x]
+    }
+
+    /// Fills a rectangle, leaving out what falls outside the canvas.
+    pub fn fill(&mut self, x: usize, y: usize, width: usize, height: usize, color: u32) {
+        let end_x = (x + width).min(self.width);
+        let end_y = (y + height).min(self.height);
+        for y in y.min(end_y)..end_y {
+            self.pixels[y * self.width + x.min(end_x)..y * self.width + end_x].fill(color);
+        }
+    }
+
+    /// Draws the outline of a rectangle one pixel wide.
+    pub fn outline(&mut self, x: usize, y: usize, width: usize, height: usize, color: u32) {
+        if width == 0 || height == 0 {
+            return;
+        }
+        self.fill(x, y, width, 1, color);
+        self.fill(x, y + height - 1, width, 1, color);
+        self.fill(x, y, 1, height, color);
+        self.fill(x + width - 1, y, 1, height, color);
+    }
+
+    /// Mixes `color` into a pixel by `coverage` out of 255, as text is
+    /// antialiased. Pixels outside the canvas are left out.
+    pub fn blend(&mut self, x: isize, y: isize, color: u32, coverage: u8) {
+        if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+            return;
+        }
+        let pixel = &mut self.pixels[y as usize * self.width + x as usize];
+        let alpha = u32::from(coverage);
+        let mix = |shift: u32| {
+            let old = (*pixel >> shift) & 0xff;
+            let new = (color >> shift) & 0xff;
+            ((new * alpha + old * (255 - alpha) + 127) / 255) << shift
+        };
+        *pixel = mix(16) | mix(8) | mix(0);
+    }
+
+    /// Encodes the canvas as an RGB PNG.
+    pub fn write_png(&self, writer: impl Write) -> Result<()> {
+        let mut encoder = png::Encoder::new(writer, self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let data: Vec<u8> = self
+            .pixels
+            .iter()
+            .flat_map(|pixel| [(pixel >> 16) as u8, (pixel >> 8) as u8, *pixel as u8])
+            .collect();
+        encoder.write_header()?.write_image_data(&data)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,201 @@
+//! Monospace text on a canvas, either from a TrueType font or from the
+//! built-in bitmap font when none is found.
+
+use crate::{bitmap, canvas::Canvas};
+use anyhow::{format_err, Context as _, Result};
+use fontdue::{FontSettings, Metrics};
+use log::info;
+use std::{collections::HashMap, fs, path::Path};
+
+/// Monospace fonts that are tried in order when none is given.
+const SYSTEM_FONTS: &[&str] = &[
+    "/usr/share/fonts/truetype/dejavu/DejaVuSansMono.ttf",
+    "/usr/share/fonts/TTF/DejaVuSansMono.ttf",
+    "/usr/share/fonts/dejavu/DejaVuSansMono.ttf",
+    "/usr/share/fonts/truetype/liberation/LiberationMono-Regular.ttf",
+    "/usr/share/fonts/liberation-mono/LiberationMono-Regular.ttf",
+    "/Library/Fonts/Menlo.ttc",
+    "/System/Library/Fonts/Menlo.ttc",
+    "C:\\Windows\\Fonts\\consola.ttf",
+];
+
+pub enum Font {
+    /// The built-in font, with every pixel drawn `scale` by `scale`.
+    Bitmap {
+        scale: usize,
+    },
+    TrueType(Box<TrueType>),
+}
+
+pub struct TrueType {
+    font: fontdue::Font,
+    size: f32,
+    cell_width: usize,
+    cell_height: usize,
+    /// From the top of a cell to the baseline, in pixels.
+    ascent: i32,
+    glyphs: HashMap<char, (Metrics, Vec<u8>)>,
+}
+
+impl Font {
+    pub fn bitmap(scale: usize) -> Font {
+        Font::Bitmap {
+            scale: scale.max(1),
+        }
+    }
+
+    /// Loads a TrueType font to draw at `size` pixels.
+    pub fn load(path: &Path, size: f32) -> Result<Font> {
+        let data = fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+        let font = fontdue::Font::from_bytes(data, FontSettings::default())
+            .map_err(|e| format_err!("loading {}: {}", path.display(), e))?;
+        let line = font
+            .horizontal_line_metrics(size)
+            .ok_or_else(|| format_err!("{} has no horizontal metrics", path.display()))?;
+        let cell_width = font.metrics('M', size).advance_width.ceil().max(1.0) as usize;
+        let cell_height = line.new_line_size.ceil().max(1.0) as usize;
+        Ok(Font::TrueType(Box::new(TrueType {
+            font,
+            size,
+            cell_width,
+            cell_height,
+            ascent: (line.ascent + line.line_gap / 2.0).round() as i32,
+            glyphs: HashMap::new(),
+        })))
+    }
+
+    /// The first of the usual monospace fonts that loads, or the bitmap font
+    /// scaled to about `size` pixels.
+    pub fn system(size: f32) -> Font {
+        for path in SYSTEM_FONTS {
+            let path = Path::new(path);
+            if path.exists() {
+                match Font::load(path, size) {
+                    Ok(font) => return font,
+                    Err(e) => info!("{e:#}"),
+                }
+            }
+        }
+        Font::bitmap((size / bitmap::CELL_HEIGHT as f32).round() as usize)
+    }
+
+    /// The width and height of a cell in pixels.
+    pub fn cell_size(&self) -> (usize, usize) {
+        match self {
+            Font::Bitmap { scale } => (bitmap::CELL_WIDTH * scale, bitmap::CELL_HEIGHT * scale),
+            Font::TrueType(font) => (font.cell_width, font.cell_height),
+        }
+    }
+
+    /// Draws a cluster into the `width` cells starting at pixel `(x, y)`.
+    /// Bold is drawn twice, a pixel apart. Characters the font doesn't have
+    /// are drawn as boxes.
+    #[allow(clippy::too_many_arguments)]
+    pub fn draw(
+        &mut self,
+        canvas: &mut Canvas,
+        x: usize,
+        y: usize,
+        cluster: &str,
+        width: usize,
+        color: u32,
+        bold: bool,
+    ) {
+        let (cell_width, cell_height) = self.cell_size();
+        let mut chars = cluster.chars();
+        let first = match chars.next() {
+            Some(c) if c != ' ' => c,
+            _ => return,
+        };
+        let drawn = match self {
+            Font::Bitmap { scale } => match bitmap::pixels(first) {
+                Some(pixels) => {
+                    let left = x + (width - 1) * cell_width / 2;
+                    for (px, py) in pixels {
+                        let (px, py) = (left + px * *scale, y + py * *scale);
+                        canvas.fill(px, py, *scale, *scale, color);
+                        if bold {
+                            canvas.fill(px + 1, py, *scale, *scale, color);
+                        }
+                    }
+                    true
+                }
+                None => false,
+            },
+            Font::TrueType(font) => {
+                if font.font.has_glyph(first) {
+                    let origin = font.draw_char(canvas, x, y, first, width, color, bold);
+                    for c in chars {
+                        if font.font.has_glyph(c) {
+                            font.draw_at(canvas, origin, y, c, color, bold);
+                        }
+                    }
+                    true
+                } else {
+                    false
+                }
+            }
+        };
+        if !drawn {
+            let margin = cell_width / 6;
+            canvas.outline(
+                x + margin,
+                y + cell_height / 5,
+                (width * cell_width).saturating_sub(2 * margin),
+                cell_height * 3 / 5,
+                color,
+            );
+        }
+    }
+}
+
+impl TrueType {
+    /// Draws `c` centered in `width` cells and returns where its origin was.
+    #[allow(clippy::too_many_arguments)]
+    fn draw_char(
+        &mut self,
+        canvas: &mut Canvas,
+        x: usize,
+        y: usize,
+        c: char,
+        width: usize,
+        color: u32,
+        bold: bool,
+    ) -> isize {
+        let advance = self.font.metrics(c, self.size).advance_width;
+        let space = (width * self.cell_width) as f32;
+        let origin = x as isize + ((space - advance) / 2.0).round().max(0.0) as isize;
+        self.draw_at(canvas, origin, y, c, color, bold);
+        origin
+    }
+
+    fn draw_at(
+        &mut self,
+        canvas: &mut Canvas,
+        origin: isize,
+        y: usize,
+        c: char,
+        color: u32,
+        bold: bool,
+    ) {
+        let (font, size) = (&self.font, self.size);
+        let (metrics, coverage) = self
+            .glyphs
+            .entry(c)
+            .or_insert_with(|| font.rasterize(c, size));
+        let left = origin + metrics.xmin as isize;
+        let top =
+            y as isize + self.ascent as isize - metrics.height as isize - metrics.ymin as isize;
+        for (i, &alpha) in coverage.iter().enumerate() {
+            if alpha == 0 {
+                continue;
+            }
+            let px = left + (i % metrics.width) as isize;
+            let py = top + (i / metrics.width) as isize;
+            canvas.blend(px, py, color, alpha);
+            if bold {
+                canvas.blend(px + 1, py, color, alpha);
+            }
+        }
+    }
+}
@@ -0,0 +1,57 @@
+//! Keys written as text, like `ihello<Esc>:w<CR>`, for scripting the editor
+//! from the command line. Special keys go in angle brackets: `<Esc>`,
+//! `<CR>`, `<Tab>`, `<BS>`, `<Left>` and the other arrows with an optional
+//! `S-` for shift, `<Home>`, `<End>`, `<PageUp>`, `<PageDown>`, `<C-x>` for
+//! control and `<lt>` for `<` itself.
+
+use anyhow::{bail, Result};
+use editor::keymap::Key;
+
+pub fn parse(text: &str) -> Result<Vec<Key>> {
+    let mut keys = Vec::new();
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        if c != '<' {
+            keys.push(Key::Char(c));
+            rest = &rest[c.len_utf8()..];
+            continue;
+        }
+        let end = match rest.find('>') {
+            Some(end) => end,
+            None => bail!("unclosed key name: {rest}"),
+        };
+        keys.push(parse_name(&rest[1..end])?);
+        rest = &rest[end + 1..];
+    }
+    Ok(keys)
+}
+
+fn parse_name(name: &str) -> Result<Key> {
+    let key = match name {
+        "Esc" => Key::Escape,
+        "CR" | "Enter" => Key::Char('\n'),
+        "Tab" => Key::Char('\t'),
+        "BS" => Key::Backspace,
+        "lt" => Key::Char('<'),
+        "Left" => Key::Left,
+        "Right" => Key::Right,
+        "Up" => Key::Up,
+        "Down" => Key::Down,
+        "S-Left" => Key::ShiftLeft,
+        "S-Right" => Key::ShiftRight,
+        "S-Up" => Key::ShiftUp,
+        "S-Down" => Key::ShiftDown,
+        "Home" => Key::Home,
+        "End" => Key::End,
+        "PageUp" => Key::PageUp,
+        "PageDown" => Key::PageDown,
+        _ => match name
+            .strip_prefix("C-")
+            .map(|c| c.chars().collect::<Vec<_>>())
+        {
+            Some(c) if c.len() == 1 => Key::Ctrl(c[0].to_ascii_lowercase()),
+            _ => bail!("unknown key: <{name}>"),
+        },
+    };
+    Ok(key)
+}
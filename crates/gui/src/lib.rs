@@ -0,0 +1,49 @@
+//! The graphical frontend. It hands keys to `editor::keymap` like the
+//! terminal frontend does, and paints the frames of `editor::render` into a
+//! canvas on the CPU, which is shown in a window or written out as PNG. No
+//! GPU or display is needed to take a screenshot.
+
+mod bitmap;
+pub mod canvas;
+pub mod font;
+pub mod keys;
+pub mod paint;
+#[cfg(feature = "window")]
+pub mod window;
+
+use canvas::Canvas;
+use editor::{keymap, render::render, EditorData};
+use font::Font;
+
+pub struct Gui {
+    pub editor: EditorData,
+    pub font: Font,
+    pub canvas: Canvas,
+}
+
+impl Gui {
+    pub fn new(editor: EditorData, font: Font) -> Gui {
+        Gui {
+            editor,
+            font,
+            canvas: Canvas::new(0, 0),
+        }
+    }
+
+    /// Turns a key press into editor actions and performs them.
+    pub fn handle_key(&mut self, key: keymap::Key) {
+        keymap::handle_key(&mut self.editor, key);
+    }
+
+    /// Draws the editor on a canvas of `width` by `height` pixels, in as many
+    /// cells as fit.
+    pub fn draw(&mut self, width: usize, height: usize) -> &Canvas {
+        if self.canvas.width != width || self.canvas.height != height {
+            self.canvas = Canvas::new(width, height);
+        }
+        let (columns, rows) = paint::grid_size(&self.font, width, height);
+        let frame = render(&mut self.editor, columns, rows);
+        paint::paint(&frame, &mut self.font, &mut self.canvas);
+        &self.canvas
+    }
+}
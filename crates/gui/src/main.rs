@@ -1,3 +1,86 @@
-fn main() {
-    println!("Hello, world!");
+use anyhow::{bail, format_err, Context as _, Result};
+use editor::EditorData;
+use gui::{font::Font, keys, Gui};
+use std::{env, fs::File, io::BufWriter, path::PathBuf};
+
+const USAGE: &str = "usage: gui [--font PATH|builtin] [--font-size PX] [--size WIDTHxHEIGHT] \
+                     [--keys KEYS] [--png PATH]";
+
+struct Args {
+    font: Option<String>,
+    font_size: f32,
+    size: (usize, usize),
+    keys: String,
+    /// Where to write a screenshot instead of opening a window.
+    png: Option<PathBuf>,
+}
+
+fn parse_args() -> Result<Args> {
+    let mut args = Args {
+        font: None,
+        font_size: 16.0,
+        size: (960, 600),
+        keys: String::new(),
+        png: None,
+    };
+    let mut iter = env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        let mut value = || {
+            iter.next()
+                .ok_or_else(|| format_err!("{arg} needs a value"))
+        };
+        match arg.as_str() {
+            "--font" => args.font = Some(value()?),
+            "--font-size" => args.font_size = value()?.parse().context("--font-size")?,
+            "--size" => {
+                let value = value()?;
+                let (width, height) = value
+                    .split_once('x')
+                    .ok_or_else(|| format_err!("--size takes WIDTHxHEIGHT, not {value}"))?;
+                args.size = (width.parse()?, height.parse()?);
+            }
+            "--keys" => args.keys = value()?,
+            "--png" => args.png = Some(value()?.into()),
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                std::process::exit(0);
+            }
+            _ => bail!("unknown argument {arg}\n{USAGE}"),
+        }
+    }
+    Ok(args)
+}
+
+fn main() -> Result<()> {
+    env_logger::init();
+    let args = parse_args()?;
+    let font = match args.font.as_deref() {
+        Some("builtin") => Font::bitmap((args.font_size / 10.0).round() as usize),
+        Some(path) => Font::load(path.as_ref(), args.font_size)?,
+        None => Font::system(args.font_size),
+    };
+    let mut gui = Gui::new(EditorData::new(), font);
+    for key in keys::parse(&args.keys)? {
+        gui.handle_key(key);
+    }
+
+    let (width, height) = args.size;
+    match args.png {
+        Some(path) => {
+            let file =
+                File::create(&path).with_context(|| format!("creating {}", path.display()))?;
+            gui.draw(width, height).write_png(BufWriter::new(file))
+        }
+        None => run(&mut gui, width, height),
+    }
+}
+
+#[cfg(feature = "window")]
+fn run(gui: &mut Gui, width: usize, height: usize) -> Result<()> {
+    gui::window::run(gui, width, height)
+}
+
+#[cfg(not(feature = "window"))]
+fn run(_gui: &mut Gui, _width: usize, _height: usize) -> Result<()> {
+    bail!("built without the window feature; use --png to take a screenshot")
 }
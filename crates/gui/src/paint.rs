@@ -0,0 +1,70 @@
+//! Paints frames of `editor::render` into a canvas: the background of every
+//! cell, then its text, then the cursors of the focused window as bars.
+
+use crate::{canvas::Canvas, font::Font};
+use editor::render::{Color, Frame, Style};
+use unicode_width::UnicodeWidthStr;
+
+pub const BACKGROUND: u32 = 0x1d1f21;
+pub const FOREGROUND: u32 = 0xc5c8c6;
+pub const CURSOR: u32 = 0xf0c674;
+
+/// The color of `color`, or `default` for the default color.
+pub fn rgb(color: Color, default: u32) -> u32 {
+    match color {
+        Color::Default => default,
+        Color::Black => 0x000000,
+        Color::Red => 0xcc6666,
+        Color::Yellow => 0xde935f,
+        Color::LightYellow => 0xf0c674,
+        Color::White => 0xffffff,
+        Color::Gray => 0x707880,
+    }
+}
+
+/// The foreground and background colors of a style.
+pub fn colors(style: Style) -> (u32, u32) {
+    let fg = rgb(style.fg, FOREGROUND);
+    let bg = rgb(style.bg, BACKGROUND);
+    if style.inverse {
+        (bg, fg)
+    } else {
+        (fg, bg)
+    }
+}
+
+/// How many cells of `font` fit in `width` by `height` pixels, at least one
+/// each way.
+pub fn grid_size(font: &Font, width: usize, height: usize) -> (usize, usize) {
+    let (cell_width, cell_height) = font.cell_size();
+    ((width / cell_width).max(1), (height / cell_height).max(1))
+}
+
+/// Paints `frame` from the top left of `canvas`. Pixels past the last cell
+/// are cleared to the background.
+pub fn paint(frame: &Frame, font: &mut Font, canvas: &mut Canvas) {
+    let (cell_width, cell_height) = font.cell_size();
+    canvas.pixels.fill(BACKGROUND);
+    let grid = frame.compose();
+    for y in 0..grid.height {
+        for (x, cell) in grid.row(y).iter().enumerate() {
+            // The cells covered by a wide cluster are painted with it.
+            if cell.text.is_empty() {
+                continue;
+            }
+            let width = cell.text.width().clamp(1, grid.width - x);
+            let (fg, bg) = colors(cell.style);
+            let (px, py) = (x * cell_width, y * cell_height);
+            canvas.fill(px, py, width * cell_width, cell_height, bg);
+            font.draw(canvas, px, py, &cell.text, width, fg, cell.style.bold);
+        }
+    }
+    let bar = (cell_width / 8).max(1);
+    for view in frame.windows.iter().filter(|view| view.focused) {
+        for cursor in &view.cursors {
+            let x = (view.area.x + cursor.x) * cell_width;
+            let y = (view.area.y + cursor.y) * cell_height;
+            canvas.fill(x, y, bar, cell_height, CURSOR);
+        }
+    }
+}
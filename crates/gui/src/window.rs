@@ -0,0 +1,113 @@
+//! Shows the editor in a window and reads keys from it.
+
+use crate::Gui;
+use anyhow::Result;
+use editor::keymap::Key;
+use minifb::{InputCallback, KeyRepeat, MouseButton, MouseMode, Window, WindowOptions};
+use std::{cell::RefCell, rc::Rc};
+
+/// Collects the text typed into the window, which comes separately from the
+/// key presses.
+struct Typed(Rc<RefCell<Vec<char>>>);
+
+impl InputCallback for Typed {
+    fn add_char(&mut self, c: u32) {
+        match char::from_u32(c) {
+            Some(c) if !c.is_control() => self.0.borrow_mut().push(c),
+            _ => {}
+        }
+    }
+}
+
+/// Runs the editor in a window of `width` by `height` pixels until it quits
+/// or the window is closed.
+pub fn run(gui: &mut Gui, width: usize, height: usize) -> Result<()> {
+    let mut window = Window::new(
+        "editor",
+        width,
+        height,
+        WindowOptions {
+            resize: true,
+            ..WindowOptions::default()
+        },
+    )?;
+    window.set_target_fps(60);
+    let typed = Rc::new(RefCell::new(Vec::new()));
+    window.set_input_callback(Box::new(Typed(typed.clone())));
+
+    let mut active = true;
+    let mut mouse_down = false;
+    let mut drawn = None;
+    while window.is_open() && !gui.editor.want_quit {
+        let mut keys: Vec<Key> = typed.borrow_mut().drain(..).map(Key::Char).collect();
+        let shift = window.is_key_down(minifb::Key::LeftShift)
+            || window.is_key_down(minifb::Key::RightShift);
+        let ctrl =
+            window.is_key_down(minifb::Key::LeftCtrl) || window.is_key_down(minifb::Key::RightCtrl);
+        keys.extend(
+            window
+                .get_keys_pressed(KeyRepeat::Yes)
+                .into_iter()
+                .filter_map(|key| editor_key(key, shift, ctrl)),
+        );
+        let down = window.get_mouse_down(MouseButton::Left);
+        if down && !mouse_down {
+            if let Some((x, y)) = window.get_mouse_pos(MouseMode::Discard) {
+                let (cell_width, cell_height) = gui.font.cell_size();
+                keys.push(Key::Click {
+                    x: x as usize / cell_width,
+                    y: y as usize / cell_height,
+                });
+            }
+        }
+        mouse_down = down;
+        if window.is_active() != active {
+            active = !active;
+            if active {
+                keys.push(Key::FocusIn);
+            }
+        }
+        for key in &keys {
+            gui.handle_key(*key);
+        }
+
+        let size = window.get_size();
+        if keys.is_empty() && drawn == Some(size) {
+            window.update();
+            continue;
+        }
+        let canvas = gui.draw(size.0, size.1);
+        window.update_with_buffer(&canvas.pixels, canvas.width, canvas.height)?;
+        drawn = Some(size);
+    }
+    Ok(())
+}
+
+/// The editor's name for a key that doesn't type text, if it has one.
+fn editor_key(key: minifb::Key, shift: bool, ctrl: bool) -> Option<Key> {
+    use minifb::Key as K;
+
+    Some(match key {
+        K::Escape => Key::Escape,
+        K::Enter | K::NumPadEnter => Key::Char('\n'),
+        K::Tab => Key::Char('\t'),
+        K::Backspace => Key::Backspace,
+        K::Left if shift => Key::ShiftLeft,
+        K::Right if shift => Key::ShiftRight,
+        K::Up if shift => Key::ShiftUp,
+        K::Down if shift => Key::ShiftDown,
+        K::Left => Key::Left,
+        K::Right => Key::Right,
+        K::Up => Key::Up,
+        K::Down => Key::Down,
+        K::Home => Key::Home,
+        K::End => Key::End,
+        K::PageUp => Key::PageUp,
+        K::PageDown => Key::PageDown,
+        // The letters are in order.
+        key if ctrl && (K::A as u32..=K::Z as u32).contains(&(key as u32)) => {
+            Key::Ctrl(char::from(b'a' + (key as u32 - K::A as u32) as u8))
+        }
+        _ => return None,
+    })
+}
@@ -0,0 +1,108 @@
+use editor::EditorData;
+use gui::{
+    canvas::Canvas,
+    font::Font,
+    keys,
+    paint::{BACKGROUND, CURSOR, FOREGROUND},
+    Gui,
+};
+
+const CELL_WIDTH: usize = 6;
+const CELL_HEIGHT: usize = 10;
+
+/// A 40 by 6 cell editor in the built-in font after typing `input`.
+fn run(input: &str) -> Gui {
+    let mut gui = Gui::new(EditorData::new(), Font::bitmap(1));
+    for key in keys::parse(input).unwrap() {
+        gui.handle_key(key);
+    }
+    gui.draw(40 * CELL_WIDTH, 6 * CELL_HEIGHT);
+    gui
+}
+
+/// The colors used in a cell.
+fn colors(canvas: &Canvas, x: usize, y: usize) -> Vec<u32> {
+    let mut colors = Vec::new();
+    for py in y * CELL_HEIGHT..(y + 1) * CELL_HEIGHT {
+        for px in x * CELL_WIDTH..(x + 1) * CELL_WIDTH {
+            let color = canvas.pixel(px, py);
+            if !colors.contains(&color) {
+                colors.push(color);
+            }
+        }
+    }
+    colors.sort_unstable();
+    colors
+}
+
+#[test]
+fn paints_text_selections_and_the_status_line() {
+    let gui = run("ihello<Esc><S-Left>");
+    let canvas = &gui.canvas;
+    assert_eq!((canvas.width, canvas.height), (240, 60));
+
+    // Text on the first line of the window, under the tab line.
+    let mut text = vec![BACKGROUND, FOREGROUND];
+    text.sort_unstable();
+    assert_eq!(colors(canvas, 0, 1), text);
+    assert_eq!(colors(canvas, 10, 1), vec![BACKGROUND]);
+    // The selection covers the "o" and the end of the line with the colors
+    // swapped, and the cursor is a bar at its end.
+    assert_eq!(canvas.pixel(3 * CELL_WIDTH + 2, CELL_HEIGHT), BACKGROUND);
+    assert_eq!(canvas.pixel(4 * CELL_WIDTH + 2, CELL_HEIGHT), FOREGROUND);
+    assert_eq!(canvas.pixel(5 * CELL_WIDTH + 2, CELL_HEIGHT), FOREGROUND);
+    assert_eq!(canvas.pixel(4 * CELL_WIDTH, CELL_HEIGHT + 5), CURSOR);
+    // The mode at the start of the status line is white on black.
+    assert_eq!(canvas.pixel(0, 5 * CELL_HEIGHT), 0xffffff);
+}
+
+#[test]
+fn insert_mode_is_shown_in_yellow() {
+    let gui = run("i");
+    assert_eq!(gui.canvas.pixel(0, 5 * CELL_HEIGHT), 0xf0c674);
+}
+
+#[test]
+fn characters_outside_the_font_are_boxes() {
+    let gui = run("i中<Esc>");
+    // The box is drawn across both cells of the wide character.
+    let canvas = &gui.canvas;
+    let row = CELL_HEIGHT + CELL_HEIGHT / 5;
+    assert!((CELL_WIDTH..2 * CELL_WIDTH - 1).all(|x| canvas.pixel(x, row) != BACKGROUND));
+}
+
+#[test]
+fn screenshots_are_pngs_of_the_canvas() {
+    let gui = run("ihi<Esc>");
+    let mut png = Vec::new();
+    gui.canvas.write_png(&mut png).unwrap();
+
+    let decoder = png::Decoder::new(png.as_slice());
+    let mut reader = decoder.read_info().unwrap();
+    let mut data = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut data).unwrap();
+    assert_eq!((info.width, info.height), (240, 60));
+    let pixels: Vec<u32> = data
+        .chunks(3)
+        .map(|rgb| u32::from_be_bytes([0, rgb[0], rgb[1], rgb[2]]))
+        .collect();
+    assert_eq!(pixels, gui.canvas.pixels);
+}
+
+#[test]
+fn parses_key_names() {
+    use editor::keymap::Key;
+    assert_eq!(
+        keys::parse("a<lt><CR><C-n><S-Up><Esc>").unwrap(),
+        vec![
+            Key::Char('a'),
+            Key::Char('<'),
+            Key::Char('\n'),
+            Key::Ctrl('n'),
+            Key::ShiftUp,
+            Key::Escape
+        ]
+    );
+    assert!(keys::parse("<Nope>").is_err());
+    assert!(keys::parse("<Esc").is_err());
+}
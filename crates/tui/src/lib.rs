@@ -0,0 +1,205 @@
+//! The terminal frontend, which turns keys into editor actions and draws
+//! the frames of `editor::render` with escape codes. It works through the
+//! `Terminal` and `KeySource` traits, so that it can run against a real
+//! terminal or an in-memory one.
+
+pub mod virtual_terminal;
+pub mod watcher;
+
+use anyhow::Result;
+use editor::{
+    keymap,
+    render::{self, render, Grid, Style},
+    EditorData,
+};
+use log::trace;
+use std::{
+    collections::VecDeque,
+    io::{self, Write},
+};
+use termion::{
+    clear,
+    color::{self, Color},
+    cursor, style,
+};
+pub use textmode::Key;
+
+/// The editor's name for a key, if it has one.
+fn editor_key(key: &Key) -> Option<keymap::Key> {
+    const SHIFT_UP: &[u8] = &[27, 91, 49, 59, 50, 65];
+    const SHIFT_DOWN: &[u8] = &[27, 91, 49, 59, 50, 66];
+    const SHIFT_RIGHT: &[u8] = &[27, 91, 49, 59, 50, 67];
+    const SHIFT_LEFT: &[u8] = &[27, 91, 49, 59, 50, 68];
+    const FOCUS_IN: &[u8] = &[27, 91, 73];
+
+    Some(match key {
+        Key::Char(c) => keymap::Key::Char(*c),
+        Key::Ctrl(b) => keymap::Key::Ctrl(char::from(*b)),
+        Key::Left => keymap::Key::Left,
+        Key::Right => keymap::Key::Right,
+        Key::Up => keymap::Key::Up,
+        Key::Down => keymap::Key::Down,
+        Key::Home => keymap::Key::Home,
+        Key::End => keymap::Key::End,
+        Key::PageUp => keymap::Key::PageUp,
+        Key::PageDown => keymap::Key::PageDown,
+        Key::Backspace => keymap::Key::Backspace,
+        Key::Escape => keymap::Key::Escape,
+        Key::Bytes(bytes) => match bytes.as_slice() {
+            SHIFT_LEFT => keymap::Key::ShiftLeft,
+            SHIFT_RIGHT => keymap::Key::ShiftRight,
+            SHIFT_UP => keymap::Key::ShiftUp,
+            SHIFT_DOWN => keymap::Key::ShiftDown,
+            FOCUS_IN => keymap::Key::FocusIn,
+            bytes => return mouse_click(bytes),
+        },
+        _ => return None,
+    })
+}
+
+/// A press of the left mouse button as reported in SGR mode, which is
+/// `ESC [ < button ; column ; row M` with one-based cells.
+fn mouse_click(bytes: &[u8]) -> Option<keymap::Key> {
+    let report = std::str::from_utf8(bytes).ok()?;
+    let report = report.strip_prefix("\x1b[<")?.strip_suffix('M')?;
+    let mut fields = report.split(';').map(|field| field.parse::<usize>().ok());
+    match (fields.next()??, fields.next()??, fields.next()??) {
+        (0, x, y) => Some(keymap::Key::Click {
+            x: x.checked_sub(1)?,
+            y: y.checked_sub(1)?,
+        }),
+        _ => None,
+    }
+}
+
+/// Where the frontend draws: anything that takes escape codes and knows its
+/// size in cells.
+pub trait Terminal: Write {
+    /// The width and height.
+    fn size(&self) -> io::Result<(u16, u16)>;
+}
+
+/// Where the frontend reads keys from. `None` means there are no more.
+pub trait KeySource {
+    fn read_key(&mut self) -> Result<Option<Key>>;
+}
+
+impl KeySource for textmode::blocking::Input {
+    fn read_key(&mut self) -> Result<Option<Key>> {
+        Ok(textmode::blocking::Input::read_key(self)?)
+    }
+}
+
+impl KeySource for VecDeque<Key> {
+    fn read_key(&mut self) -> Result<Option<Key>> {
+        Ok(self.pop_front())
+    }
+}
+
+pub struct Tui<T> {
+    pub editor: EditorData,
+    pub terminal: T,
+    /// What was drawn last, to redraw only what changed.
+    pub screen: Option<Grid>,
+}
+
+impl<T: Terminal> Tui<T> {
+    pub fn new(editor: EditorData, terminal: T) -> Tui<T> {
+        Tui {
+            editor,
+            terminal,
+            screen: None,
+        }
+    }
+
+    /// Handles every key from `keys` and draws after each, until there are
+    /// no more keys or the editor wants to quit.
+    pub fn run(&mut self, keys: &mut impl KeySource) -> Result<()> {
+        self.draw()?;
+        while !self.editor.want_quit {
+            let key = match keys.read_key()? {
+                Some(key) => key,
+                None => break,
+            };
+            self.handle_key(key)?;
+            self.draw()?;
+        }
+        Ok(())
+    }
+
+    /// Turns a key press into editor actions and performs them.
+    pub fn handle_key(&mut self, key: Key) -> Result<()> {
+        trace!("event: {:?}", key);
+        if let Some(key) = editor_key(&key) {
+            keymap::handle_key(&mut self.editor, key);
+        }
+        Ok(())
+    }
+
+    /// Draws the editor, writing only the cells that changed since the
+    /// last time.
+    pub fn draw(&mut self) -> Result<()> {
+        let (width, height) = self.terminal.size()?;
+        let frame = render(&mut self.editor, usize::from(width), usize::from(height));
+        let grid = frame.compose();
+        let old = self
+            .screen
+            .take()
+            .filter(|old| old.width == grid.width && old.height == grid.height);
+        if old.is_none() {
+            write!(self.terminal, "{}", clear::All)?;
+        }
+        // Where the terminal's cursor and style are after the last run, if
+        // known.
+        let mut position = None;
+        let mut current = None;
+        for damage in render::damage(old.as_ref(), &grid) {
+            if position != Some((damage.columns.start, damage.y)) {
+                let goto = cursor::Goto(damage.columns.start as u16 + 1, damage.y as u16 + 1);
+                write!(self.terminal, "{}", goto)?;
+            }
+            for cell in &grid.row(damage.y)[damage.columns.clone()] {
+                if current != Some(cell.style) {
+                    write_style(&mut self.terminal, cell.style)?;
+                    current = Some(cell.style);
+                }
+                write!(self.terminal, "{}", cell.text)?;
+            }
+            position = Some((damage.columns.end, damage.y));
+        }
+        write!(self.terminal, "{}", style::Reset)?;
+        self.screen = Some(grid);
+        self.terminal.flush()?;
+        Ok(())
+    }
+}
+
+/// Switches the terminal to `style`, from whatever it was.
+fn write_style(tty: &mut impl Write, style: Style) -> Result<()> {
+    write!(tty, "{}", style::Reset)?;
+    if style.bold {
+        write!(tty, "{}", style::Bold)?;
+    }
+    if style.inverse {
+        write!(tty, "{}", style::Invert)?;
+    }
+    if style.fg != render::Color::Default {
+        write!(tty, "{}", color::Fg(terminal_color(style.fg)))?;
+    }
+    if style.bg != render::Color::Default {
+        write!(tty, "{}", color::Bg(terminal_color(style.bg)))?;
+    }
+    Ok(())
+}
+
+fn terminal_color(color: render::Color) -> &'static dyn Color {
+    match color {
+        render::Color::Default => &color::Reset,
+        render::Color::Black => &color::Black,
+        render::Color::Red => &color::Red,
+        render::Color::Yellow => &color::Yellow,
+        render::Color::LightYellow => &color::LightYellow,
+        render::Color::White => &color::White,
+        render::Color::Gray => &color::LightBlack,
+    }
+}
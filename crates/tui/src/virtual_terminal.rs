@@ -0,0 +1,195 @@
+//! A terminal that only exists in memory. It understands the escape codes
+//! the frontend writes and keeps the resulting screen as a grid of styled
+//! cells, so that tests can check what a real terminal would show.
+
+use crate::Terminal;
+use editor::{
+    display,
+    render::{Color, Grid, Style},
+};
+use std::io;
+use unicode_segmentation::UnicodeSegmentation;
+
+pub struct VirtualTerminal {
+    screen: Grid,
+    x: usize,
+    y: usize,
+    style: Style,
+    /// Output not applied to the screen yet, which happens on flush.
+    pending: Vec<u8>,
+    /// The number of bytes written so far.
+    pub written: usize,
+}
+
+impl VirtualTerminal {
+    pub fn new(width: usize, height: usize) -> VirtualTerminal {
+        VirtualTerminal {
+            screen: Grid::new(width, height),
+            x: 0,
+            y: 0,
+            style: Style::default(),
+            pending: Vec::new(),
+            written: 0,
+        }
+    }
+
+    pub fn screen(&self) -> &Grid {
+        &self.screen
+    }
+
+    /// Changes the size, which clears the screen like many terminals do.
+    pub fn resize(&mut self, width: usize, height: usize) {
+        self.screen = Grid::new(width, height);
+        self.x = 0;
+        self.y = 0;
+    }
+
+    /// The rows of the screen without trailing spaces.
+    pub fn lines(&self) -> Vec<String> {
+        (0..self.screen.height)
+            .map(|y| self.screen.row_text(y).trim_end().to_owned())
+            .collect()
+    }
+
+    fn apply(&mut self) {
+        let pending = std::mem::take(&mut self.pending);
+        let output = String::from_utf8_lossy(&pending);
+        let mut rest = &*output;
+        while !rest.is_empty() {
+            match rest.find('\x1b') {
+                Some(0) => rest = self.escape(&rest[1..]),
+                Some(i) => {
+                    self.text(&rest[..i]);
+                    rest = &rest[i..];
+                }
+                None => {
+                    self.text(rest);
+                    rest = "";
+                }
+            }
+        }
+    }
+
+    fn text(&mut self, text: &str) {
+        for cluster in text.graphemes(true) {
+            match cluster {
+                "\r" => self.x = 0,
+                "\n" | "\r\n" => self.y += 1,
+                _ => {
+                    let width = display::cluster_width(cluster);
+                    if width == 0 {
+                        continue;
+                    }
+                    if self.x + width > self.screen.width {
+                        self.x = 0;
+                        self.y += 1;
+                    }
+                    if self.y >= self.screen.height {
+                        return;
+                    }
+                    self.x = self.screen.put(self.x, self.y, cluster, width, self.style);
+                }
+            }
+        }
+    }
+
+    /// Applies the escape sequence after an `ESC`, and returns what follows
+    /// it.
+    fn escape<'a>(&mut self, rest: &'a str) -> &'a str {
+        let rest = match rest.strip_prefix('[') {
+            Some(rest) => rest,
+            // Two char sequences, none of which change the screen.
+            None => return rest.get(1..).unwrap_or_default(),
+        };
+        let end = match rest.find(|c: char| ('\x40'..='\x7e').contains(&c)) {
+            Some(end) => end,
+            None => return "",
+        };
+        let params = &rest[..end];
+        let numbers: Vec<usize> = params.split(';').map(|n| n.parse().unwrap_or(0)).collect();
+        let number = |i: usize, default: usize| match numbers.get(i) {
+            Some(0) | None => default,
+            Some(&n) => n,
+        };
+        match &rest[end..end + 1] {
+            _ if params.starts_with('?') || params.ends_with(' ') => {}
+            "H" => {
+                self.y = number(0, 1) - 1;
+                self.x = number(1, 1) - 1;
+            }
+            "J" if number(0, 0) == 2 => {
+                self.screen = Grid::new(self.screen.width, self.screen.height);
+            }
+            "K" if number(0, 0) == 2 && self.y < self.screen.height => {
+                for x in 0..self.screen.width {
+                    self.screen.put(x, self.y, " ", 1, Style::default());
+                }
+            }
+            "m" => self.select_graphic_rendition(&numbers),
+            _ => {}
+        }
+        &rest[end + 1..]
+    }
+
+    fn select_graphic_rendition(&mut self, numbers: &[usize]) {
+        let mut numbers = numbers.iter().copied();
+        while let Some(n) = numbers.next() {
+            match n {
+                0 => self.style = Style::default(),
+                1 => self.style.bold = true,
+                7 => self.style.inverse = true,
+                22 => self.style.bold = false,
+                27 => self.style.inverse = false,
+                30..=37 => self.style.fg = color(n - 30),
+                39 => self.style.fg = Color::Default,
+                40..=47 => self.style.bg = color(n - 40),
+                49 => self.style.bg = Color::Default,
+                38 | 48 => {
+                    let color = match (numbers.next(), numbers.next()) {
+                        (Some(5), Some(index)) => color(index),
+                        _ => Color::Default,
+                    };
+                    if n == 38 {
+                        self.style.fg = color;
+                    } else {
+                        self.style.bg = color;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// The color with the given index in the 256 color palette, as far as
+/// the frontend uses them.
+fn color(index: usize) -> Color {
+    match index {
+        0 => Color::Black,
+        1 => Color::Red,
+        3 => Color::Yellow,
+        7 => Color::White,
+        8 => Color::Gray,
+        11 => Color::LightYellow,
+        _ => Color::Default,
+    }
+}
+
+impl io::Write for VirtualTerminal {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.pending.extend_from_slice(buf);
+        self.written += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.apply();
+        Ok(())
+    }
+}
+
+impl Terminal for VirtualTerminal {
+    fn size(&self) -> io::Result<(u16, u16)> {
+        Ok((self.screen.width as u16, self.screen.height as u16))
+    }
+}
@@ -0,0 +1,114 @@
+//! Watches the directories of all open files and reports the paths that were
+//! changed in them. Directories are watched instead of the files themselves
+//! so that files replaced by renaming over them are still noticed.
+
+pub use imp::Watcher;
+
+#[cfg(target_os = "linux")]
+mod imp {
+    use anyhow::Result;
+    use crossbeam_channel::{unbounded, Receiver};
+    use editor::EditorData;
+    use inotify::{Inotify, WatchDescriptor, WatchMask, Watches};
+    use log::error;
+    use std::{
+        collections::{HashMap, HashSet},
+        path::PathBuf,
+        sync::{Arc, Mutex},
+        thread,
+    };
+
+    pub struct Watcher {
+        watches: Watches,
+        dirs: Arc<Mutex<HashMap<WatchDescriptor, PathBuf>>>,
+        pub changes: Receiver<PathBuf>,
+    }
+
+    impl Watcher {
+        pub fn new() -> Result<Watcher> {
+            let mut inotify = Inotify::init()?;
+            let watches = inotify.watches();
+            let dirs = Arc::new(Mutex::new(HashMap::<WatchDescriptor, PathBuf>::new()));
+            let (change_s, change_r) = unbounded();
+            let thread_dirs = Arc::clone(&dirs);
+            thread::spawn(move || {
+                let mut buffer = [0; 4096];
+                loop {
+                    let events = match inotify.read_events_blocking(&mut buffer) {
+                        Ok(events) => events,
+                        Err(err) => {
+                            error!("failed to read inotify events: {}", err);
+                            return;
+                        }
+                    };
+                    for event in events {
+                        let path = match (thread_dirs.lock().unwrap().get(&event.wd), event.name) {
+                            (Some(dir), Some(name)) => dir.join(name),
+                            _ => continue,
+                        };
+                        if change_s.send(path).is_err() {
+                            return;
+                        }
+                    }
+                }
+            });
+            Ok(Watcher {
+                watches,
+                dirs,
+                changes: change_r,
+            })
+        }
+
+        /// Starts watching the directories of newly opened buffers and stops
+        /// watching the ones no open buffer is in anymore.
+        pub fn sync(&mut self, editor: &EditorData) {
+            let wanted = editor
+                .buffers
+                .iter()
+                .filter_map(|buffer| Some(buffer.path.as_ref()?.parent()?.to_owned()))
+                .collect::<HashSet<PathBuf>>();
+            let mut dirs = self.dirs.lock().unwrap();
+            let watches = &mut self.watches;
+            dirs.retain(|wd, dir| {
+                let keep = wanted.contains(dir);
+                if !keep {
+                    _ = watches.remove(wd.clone());
+                }
+                keep
+            });
+            let watched = dirs.values().cloned().collect::<HashSet<PathBuf>>();
+            for dir in wanted.difference(&watched) {
+                let mask = WatchMask::CLOSE_WRITE
+                    | WatchMask::MOVED_TO
+                    | WatchMask::CREATE
+                    | WatchMask::DELETE;
+                match watches.add(dir, mask) {
+                    Ok(wd) => {
+                        dirs.insert(wd, dir.clone());
+                    }
+                    Err(err) => error!("failed to watch '{}': {}", dir.display(), err),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod imp {
+    use anyhow::Result;
+    use crossbeam_channel::{never, Receiver};
+    use editor::EditorData;
+    use std::path::PathBuf;
+
+    pub struct Watcher {
+        pub changes: Receiver<PathBuf>,
+    }
+
+    impl Watcher {
+        pub fn new() -> Result<Watcher> {
+            Ok(Watcher { changes: never() })
+        }
+
+        pub fn sync(&mut self, _editor: &EditorData) {}
+    }
+}
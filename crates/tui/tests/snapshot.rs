@@ -0,0 +1,318 @@
+use editor::{
+    location::{CharIndex, Selection},
+    options::LineNumbers,
+    render::{Color, Style},
+    run_command, EditorData,
+};
+use std::{collections::VecDeque, fs, path::PathBuf, thread, time::Duration};
+use tui::{virtual_terminal::VirtualTerminal, Key, Tui};
+
+const SHIFT_RIGHT: &[u8] = &[27, 91, 49, 59, 50, 67];
+
+fn tui(text: &str) -> Tui<VirtualTerminal> {
+    let mut editor = EditorData::new();
+    let window = &editor.windows[editor.focused_window()];
+    editor.buffers[window.buffer].content = ropey::Rope::from(text);
+    Tui::new(editor, VirtualTerminal::new(40, 6))
+}
+
+fn keys(text: &str) -> VecDeque<Key> {
+    text.chars()
+        .map(|c| match c {
+            '\x1b' => Key::Escape,
+            '\x17' => Key::Ctrl(b'w'),
+            '\x10' => Key::Ctrl(b'p'),
+            '\n' => Key::Char('\n'),
+            c => Key::Char(c),
+        })
+        .collect()
+}
+
+fn run(tui: &mut Tui<VirtualTerminal>, mut keys: VecDeque<Key>) {
+    tui.run(&mut keys).unwrap();
+    // The terminal must show exactly what the frontend thinks it drew.
+    assert_eq!(Some(tui.terminal.screen()), tui.screen.as_ref());
+}
+
+#[test]
+fn draws_tabs_text_and_status() {
+    let mut tui = tui("first line\nsecond\n");
+    run(&mut tui, VecDeque::new());
+    assert_eq!(
+        tui.terminal.lines(),
+        [
+            "scratch",
+            "first line",
+            "second",
+            "",
+            "",
+            " Normal  scratch [UTF-8 unix]   1:1 50%",
+        ]
+    );
+    let screen = tui.terminal.screen();
+    assert!(screen.cell(0, 0).style.bold);
+    assert_eq!(screen.cell(0, 1).style, Style::inverse());
+    assert_eq!(screen.cell(1, 1).style, Style::default());
+    assert!(screen.cell(1, 5).style.inverse);
+}
+
+#[test]
+fn typing_inserts_and_marks_the_buffer_modified() {
+    let mut tui = tui("\n");
+    run(&mut tui, keys("ihello\x1b"));
+    let lines = tui.terminal.lines();
+    assert_eq!(lines[0], "scratch [+]");
+    assert_eq!(lines[1], "hello");
+    // The right side gives way to what doesn't fit on the left.
+    assert_eq!(lines[5], " Normal  scratch [+] [UTF-8 unix]  100%");
+}
+
+#[test]
+fn the_status_line_follows_its_template() {
+    let mut editor = EditorData::new();
+    let window_id = editor.focused_window();
+    let window = &mut editor.windows[window_id];
+    window.selections.insert(Selection::new(CharIndex(4)));
+    let buffer = &mut editor.buffers[window.buffer];
+    buffer.content = ropey::Rope::from("one\ntwo\nthree\n");
+    buffer.name = String::from("main.rs");
+    let mut tui = Tui::new(editor, VirtualTerminal::new(40, 6));
+    run(
+        &mut tui,
+        keys(":set 'statusline={name} {modified} {filetype}{=}{selections} {line}/{column}'\nj"),
+    );
+    assert_eq!(
+        tui.terminal.lines()[5],
+        " Normal main.rs rust           2 sel 2/1"
+    );
+}
+
+#[test]
+fn insert_mode_is_shown_in_yellow() {
+    let mut tui = tui("\n");
+    run(&mut tui, keys("i"));
+    let cell = tui.terminal.screen().cell(1, 5);
+    assert_eq!(cell.text, "I");
+    assert_eq!(cell.style.fg, Color::LightYellow);
+    assert!(cell.style.inverse);
+}
+
+#[test]
+fn extending_a_selection_highlights_it() {
+    let mut tui = tui("abcdef\n");
+    let mut keys = keys("l");
+    keys.extend(std::iter::repeat_n(Key::Bytes(SHIFT_RIGHT.to_vec()), 3));
+    run(&mut tui, keys);
+    let screen = tui.terminal.screen();
+    let inverted: Vec<bool> = (0..6).map(|x| screen.cell(x, 1).style.inverse).collect();
+    assert_eq!(inverted, [false, true, true, true, true, false]);
+}
+
+#[test]
+fn set_command_turns_on_line_numbers() {
+    let mut tui = tui("a\nb\nc\n");
+    run(&mut tui, keys(":set number=relative\nj"));
+    let lines = tui.terminal.lines();
+    assert_eq!(&lines[1..4], ["1 a", "0 b", "1 c"]);
+    assert_eq!(tui.terminal.screen().cell(0, 2).style, Style::bold());
+    assert_eq!(tui.terminal.screen().cell(0, 1).style.fg, Color::Gray);
+}
+
+#[test]
+fn errors_are_shown_on_a_red_status_line() {
+    let mut tui = tui("\n");
+    run(&mut tui, keys(":nosuchcommand\n"));
+    let screen = tui.terminal.screen();
+    assert!(tui.terminal.lines()[5].contains("nosuchcommand"));
+    assert_eq!(screen.cell(0, 5).style.bg, Color::Red);
+    // The message goes away with the next key.
+    run(&mut tui, keys("i\x1b"));
+    assert!(tui.terminal.lines()[5].starts_with(" Normal"));
+}
+
+#[test]
+fn wide_characters_take_two_cells() {
+    let mut tui = tui("\u{4e2d}\u{6587}x\n");
+    run(&mut tui, keys("ll"));
+    let screen = tui.terminal.screen();
+    assert_eq!(screen.cell(0, 1).text, "\u{4e2d}");
+    assert_eq!(screen.cell(1, 1).text, "");
+    assert_eq!(screen.cell(4, 1).text, "x");
+    assert!(screen.cell(4, 1).style.inverse);
+}
+
+#[test]
+fn moving_the_cursor_redraws_only_what_changed() {
+    let text: String = (0..4).map(|i| format!("line {}\n", i)).collect();
+    let mut tui = tui(&text);
+    run(&mut tui, VecDeque::new());
+    let full = tui.terminal.written;
+    run(&mut tui, keys("l"));
+    let update = tui.terminal.written - full;
+    assert!(
+        update * 4 < full,
+        "{} bytes to move, {} to draw",
+        update,
+        full
+    );
+}
+
+#[test]
+fn resizing_redraws_everything() {
+    let mut tui = tui("some text\n");
+    run(&mut tui, VecDeque::new());
+    tui.terminal.resize(20, 4);
+    run(&mut tui, VecDeque::new());
+    assert_eq!(tui.terminal.lines()[1], "some text");
+    assert_eq!(tui.terminal.screen().width, 20);
+}
+
+#[test]
+fn vsplit_shows_the_buffer_twice_with_a_border() {
+    let mut tui = tui("one\ntwo\n");
+    run(&mut tui, keys(":vsplit\n"));
+    let lines = tui.terminal.lines();
+    assert_eq!(lines[1], format!("{:20}\u{2502}one", "one"));
+    assert_eq!(lines[2], format!("{:20}\u{2502}two", "two"));
+    assert_eq!(lines[4], format!("{:20}\u{2502}", ""));
+    assert_eq!(tui.terminal.screen().cell(20, 1).style.fg, Color::Gray);
+}
+
+#[test]
+fn windows_on_one_buffer_keep_their_own_selections() {
+    let mut tui = tui("abc\n");
+    // The new window goes below and takes the focus.
+    run(&mut tui, keys(":split\nll\x17kiX\x1b"));
+    let lines = tui.terminal.lines();
+    assert_eq!(lines[1], "Xabc");
+    assert_eq!(lines[3], "\u{2500}".repeat(40));
+    assert_eq!(lines[4], "Xabc");
+    let screen = tui.terminal.screen();
+    let inverted: Vec<bool> = (0..5).map(|x| screen.cell(x, 4).style.inverse).collect();
+    assert_eq!(inverted, [false, false, false, true, false]);
+}
+
+#[test]
+fn resizing_a_window_moves_the_border() {
+    let mut tui = tui("text\n");
+    run(&mut tui, keys(":split\n:resize +1\n"));
+    let lines = tui.terminal.lines();
+    assert_eq!(lines[1], "text");
+    assert_eq!(lines[2], "\u{2500}".repeat(40));
+    assert_eq!(lines[3], "text");
+    run(&mut tui, keys(":resize 1\n"));
+    assert_eq!(tui.terminal.lines()[3], "\u{2500}".repeat(40));
+}
+
+#[test]
+fn closing_a_window_gives_its_space_back() {
+    let mut tui = tui("text\n");
+    run(&mut tui, keys(":vsplit\n:close\n"));
+    assert_eq!(tui.terminal.lines()[1], "text");
+    run(&mut tui, keys(":close\n"));
+    assert!(tui.terminal.lines()[5].contains("cannot close the last window"));
+}
+
+#[test]
+fn tab_commands_open_move_and_close_tabs() {
+    let mut tui = tui("\n");
+    run(&mut tui, keys(":tabnew\n:tabnew\n"));
+    assert_eq!(tui.terminal.lines()[0], "scratch scratch 2 scratch 3");
+    run(&mut tui, keys(":tabmove 0\n"));
+    assert_eq!(tui.terminal.lines()[0], "scratch 3 scratch scratch 2");
+    assert!(tui.terminal.screen().cell(0, 0).style.bold);
+    run(&mut tui, keys(":tab 3\n"));
+    assert!(tui.terminal.screen().cell(18, 0).style.bold);
+    run(&mut tui, keys(":tabclose\n"));
+    assert_eq!(tui.terminal.lines()[0], "scratch 3 scratch");
+    assert!(tui.terminal.screen().cell(10, 0).style.bold);
+    run(&mut tui, keys(":tabclose 1\n:tabclose\n"));
+    let lines = tui.terminal.lines();
+    assert_eq!(lines[0], "scratch");
+    assert!(lines[5].contains("cannot close the last window"));
+}
+
+#[test]
+fn previous_tab_wraps_around_from_the_first() {
+    let mut tui = tui("\n");
+    run(&mut tui, keys(":tabnew\n:tab 1\n\x10"));
+    assert_eq!(tui.editor.focused_tab, 1);
+}
+
+#[test]
+fn the_tab_line_scrolls_to_the_focused_tab() {
+    let mut tui = tui("\n");
+    run(&mut tui, keys(&":tabnew\n".repeat(4)));
+    assert_eq!(tui.terminal.lines()[0], "< scratch 3 scratch 4 scratch 5");
+    run(&mut tui, keys(":tab 1\n"));
+    assert_eq!(
+        tui.terminal.lines()[0],
+        "scratch scratch 2 scratch 3 scratch 4  >"
+    );
+}
+
+#[test]
+fn clicking_the_tab_line_switches_tabs() {
+    let click = |x: usize| Key::Bytes(format!("\x1b[<0;{};1M", x + 1).into_bytes());
+    let mut tui = tui("\n");
+    run(&mut tui, keys(&":tabnew\n".repeat(4)));
+    assert_eq!(tui.terminal.lines()[0], "< scratch 3 scratch 4 scratch 5");
+    run(&mut tui, VecDeque::from([click(12)]));
+    assert_eq!(tui.editor.focused_tab, 3);
+    // The `>` and `<` step to the tabs that are cut off.
+    assert!(tui.terminal.lines()[0].ends_with('>'));
+    run(&mut tui, VecDeque::from([click(39)]));
+    assert_eq!(tui.editor.focused_tab, 4);
+    run(&mut tui, VecDeque::from([click(0)]));
+    assert_eq!(tui.editor.focused_tab, 3);
+}
+
+#[test]
+fn large_files_are_numbered_from_the_start_of_the_file() {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("large-numbers");
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("big.txt");
+    // Two pages of 20 byte lines.
+    let text: String = (0..100_000).map(|i| format!("{:019}\n", i)).collect();
+    fs::write(&path, text).unwrap();
+
+    let mut editor = EditorData::new();
+    editor.options.large_file_threshold = 1;
+    editor.options.line_numbers = LineNumbers::Absolute;
+    // Leave the long path out, so the position fits.
+    run_command(&mut editor, &["set", "stl={=} {line}:{column} {percent} "]).unwrap();
+    run_command(&mut editor, &["open", path.to_str().unwrap()]).unwrap();
+    let buffer_id = editor.windows[editor.focused_window()].buffer;
+    let large = editor.buffers[buffer_id].large.as_ref().unwrap();
+    while large.lines.total().is_none() {
+        thread::sleep(Duration::from_millis(1));
+    }
+    editor.switch_page(buffer_id, 1).unwrap();
+
+    let mut tui = Tui::new(editor, VirtualTerminal::new(40, 6));
+    run(&mut tui, VecDeque::new());
+    let lines = tui.terminal.lines();
+    // The second page starts with the line after the first newline past
+    // 1 MiB, which is line 52430.
+    assert_eq!(lines[1], " 52430 0000000000000052429");
+    assert_eq!(lines[2], " 52431 0000000000000052430");
+    assert!(lines[5].ends_with(" 52430:1 52%"), "{}", lines[5]);
+}
+
+#[test]
+fn hex_cursors_follow_edits_made_in_other_windows() {
+    let mut editor = EditorData::new();
+    let window = &editor.windows[editor.focused_window()];
+    let buffer = &mut editor.buffers[window.buffer];
+    buffer.content = ropey::Rope::new();
+    buffer.binary = Some(vec![1, 2, 3]);
+    let mut tui = Tui::new(editor, VirtualTerminal::new(60, 6));
+    // The new window goes to the append position, then a byte is deleted
+    // through the other one.
+    run(&mut tui, keys("\x17sgj\x17wd\x17wia\x1b"));
+    let editor = &tui.editor;
+    let buffer = &editor.buffers[editor.windows[editor.focused_window()].buffer];
+    assert_eq!(buffer.binary.as_deref(), Some(&[2, 3, 0xa0][..]));
+    let lines = tui.terminal.lines();
+    assert!(lines[1].starts_with("00000000  02 03 a0"), "{}", lines[1]);
+}
@@ -0,0 +1,101 @@
+#![cfg(target_os = "linux")]
+
+use editor::{
+    handle_file_change, perform_action, run_command, Action, EditorData, Importance, WindowAction,
+};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tui::watcher::Watcher;
+
+/// An empty directory for one test to write files in.
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR"))
+        .join("watcher")
+        .join(name);
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir.canonicalize().unwrap()
+}
+
+/// An editor with `path` open, and a watcher on its directory.
+fn watch(path: &Path) -> (EditorData, Watcher) {
+    let mut editor = EditorData::new();
+    run_command(&mut editor, &["open", path.to_str().unwrap()]).unwrap();
+    let mut watcher = Watcher::new().unwrap();
+    watcher.sync(&editor);
+    (editor, watcher)
+}
+
+/// Passes the changes the watcher reports to the editor until one is about
+/// `path`.
+fn wait_for_change(editor: &mut EditorData, watcher: &Watcher, path: &Path) {
+    loop {
+        let changed = watcher
+            .changes
+            .recv_timeout(Duration::from_secs(5))
+            .expect("no change reported");
+        handle_file_change(editor, &changed);
+        if changed == *path {
+            return;
+        }
+    }
+}
+
+fn content(editor: &EditorData) -> String {
+    editor.buffers[editor.windows[editor.focused_window()].buffer]
+        .content
+        .to_string()
+}
+
+#[test]
+fn writing_a_watched_file_reloads_it() {
+    let dir = temp_dir("write");
+    let path = dir.join("a.txt");
+    fs::write(&path, "old\n").unwrap();
+    let (mut editor, watcher) = watch(&path);
+
+    fs::write(&path, "changed\n").unwrap();
+    wait_for_change(&mut editor, &watcher, &path);
+    assert_eq!(content(&editor), "changed\n");
+    assert!(editor.pending_message.is_none());
+}
+
+#[test]
+fn renaming_over_a_modified_file_reports_a_conflict() {
+    let dir = temp_dir("rename");
+    let path = dir.join("a.txt");
+    fs::write(&path, "old\n").unwrap();
+    let (mut editor, watcher) = watch(&path);
+    let insert = Action::Window(WindowAction::InsertAtSelectionStart('x'));
+    perform_action(&mut editor, insert).unwrap();
+
+    let other = dir.join("a.txt.new");
+    fs::write(&other, "replaced\n").unwrap();
+    fs::rename(&other, &path).unwrap();
+    wait_for_change(&mut editor, &watcher, &path);
+    // The unsaved change is kept.
+    assert_eq!(content(&editor), "xold\n");
+    match &editor.pending_message {
+        Some((Importance::Warning, message)) => {
+            assert!(message.contains("changed on disk"), "{}", message)
+        }
+        message => panic!("expected a warning, got {:?}", message),
+    }
+}
+
+#[test]
+fn renaming_over_an_unmodified_file_reloads_it() {
+    let dir = temp_dir("rename-reload");
+    let path = dir.join("a.txt");
+    fs::write(&path, "old\n").unwrap();
+    let (mut editor, watcher) = watch(&path);
+
+    let other = dir.join("a.txt.new");
+    fs::write(&other, "replaced\n").unwrap();
+    fs::rename(&other, &path).unwrap();
+    wait_for_change(&mut editor, &watcher, &path);
+    assert_eq!(content(&editor), "replaced\n");
+}